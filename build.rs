@@ -1,4 +1,6 @@
 fn main() {
+    println!("cargo:rerun-if-changed=icon.rc");
+    println!("cargo:rerun-if-changed=assets/rustysvg2ico.ico");
     if std::env::var("CARGO_CFG_TARGET_OS").unwrap() == "windows" {
         embed_resource::compile("icon.rc", std::iter::empty::<&str>());
     }
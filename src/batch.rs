@@ -0,0 +1,59 @@
+use std::path::{Path, PathBuf};
+
+use crate::{convert_svg_file, ConvertOptions, Error};
+
+/// Outcome of converting one file in a batch.
+#[derive(Debug)]
+pub struct BatchItem {
+    /// The SVG that was converted.
+    pub source: PathBuf,
+    /// Where the ICO was (or would have been) written.
+    pub output: PathBuf,
+    pub result: Result<(), Error>,
+}
+
+/// Recursively collects every `.svg` file under `dir`, sorted by path. Symlinked folders are skipped.
+pub fn find_svgs(dir: &Path) -> Result<Vec<PathBuf>, Error> {
+    let mut found = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(current) = pending.pop() {
+        for entry in std::fs::read_dir(&current)? {
+            let entry = entry?;
+            let path = entry.path();
+            // The entry's own type, so symlinked folders (and symlink loops) are not followed
+            if entry.file_type()?.is_dir() {
+                pending.push(path);
+            } else if path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("svg")) {
+                found.push(path);
+            }
+        }
+    }
+    found.sort();
+    Ok(found)
+}
+
+/// Converts every SVG under `input_dir` into an ICO at the same relative path under `output_dir`.
+///
+/// Only a failure to list `input_dir` is returned as an error; each file's own outcome is
+/// reported in the returned items so one broken SVG does not stop the rest of the batch.
+pub fn convert_dir(input_dir: &Path, output_dir: &Path, options: &ConvertOptions) -> Result<Vec<BatchItem>, Error> {
+    let items = find_svgs(input_dir)?
+        .into_iter()
+        .map(|source| {
+            let relative = source.strip_prefix(input_dir).unwrap_or(&source);
+            let output = output_dir.join(relative).with_extension("ico");
+            let result = convert_one(&source, &output, options);
+            BatchItem { source, output, result }
+        })
+        .collect();
+    Ok(items)
+}
+
+fn convert_one(source: &Path, output: &Path, options: &ConvertOptions) -> Result<(), Error> {
+    let ico_data = convert_svg_file(source, options)?.to_ico_bytes()?;
+    if let Some(parent) = output.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(output, ico_data)?;
+    Ok(())
+}
@@ -0,0 +1,62 @@
+//! Helpers for `build.rs` scripts that turn an SVG into the application icon at compile time.
+//!
+//! ```ignore
+//! // build.rs, with rusty_svg2ico and embed-resource as build-dependencies
+//! fn main() {
+//!     let rc = rusty_svg2ico::build::compile_icon_rc("assets/app.svg", &[256, 48, 32, 16]).unwrap();
+//!     if std::env::var("CARGO_CFG_TARGET_OS").unwrap() == "windows" {
+//!         embed_resource::compile(rc, embed_resource::NONE);
+//!     }
+//! }
+//! ```
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::{convert_svg, rc, ConvertOptions, Error};
+
+/// Converts `svg` into `<OUT_DIR>/<svg name>.ico` with the given sizes and returns the ICO path.
+///
+/// Prints `cargo:rerun-if-changed` for the SVG, so the build script only runs again when it
+/// changes. Even then the ICO is only rewritten when the SVG or the sizes differ from the last
+/// build, which a hash stamp next to it records. Relative paths are resolved from the package root.
+pub fn compile_icon(svg: impl AsRef<Path>, sizes: &[u16]) -> Result<PathBuf, Error> {
+    let svg = svg.as_ref();
+    println!("cargo:rerun-if-changed={}", svg.display());
+
+    let out_dir = std::env::var_os("OUT_DIR")
+        .map(PathBuf::from)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "OUT_DIR is not set; call this from a build script"))?;
+    let stem = svg.file_stem().map_or_else(|| "icon".into(), |stem| stem.to_string_lossy().into_owned());
+    let ico_path = out_dir.join(format!("{}.ico", stem));
+    let stamp_path = out_dir.join(format!("{}.ico.stamp", stem));
+
+    let data = std::fs::read(svg).map_err(|err| io::Error::new(err.kind(), format!("{}: {}", svg.display(), err)))?;
+    // The crate version is part of the stamp, since a newer renderer may draw the same SVG differently
+    let mut hasher = DefaultHasher::new();
+    (&data, sizes, env!("CARGO_PKG_VERSION")).hash(&mut hasher);
+    let stamp = format!("{:016x}", hasher.finish());
+
+    let up_to_date = ico_path.exists() && std::fs::read_to_string(&stamp_path).is_ok_and(|old| old == stamp);
+    if !up_to_date {
+        let options = ConvertOptions { sizes: sizes.to_vec(), ..ConvertOptions::default() };
+        std::fs::write(&ico_path, convert_svg(&data, &options)?.to_ico_bytes()?)?;
+        std::fs::write(&stamp_path, stamp)?;
+    }
+    Ok(ico_path)
+}
+
+/// Runs [`compile_icon`] and writes `<OUT_DIR>/<svg name>.rc` declaring the ICO as the application
+/// icon. Returns the `.rc` path, ready for `embed_resource::compile`.
+pub fn compile_icon_rc(svg: impl AsRef<Path>, sizes: &[u16]) -> Result<PathBuf, Error> {
+    let ico_path = compile_icon(svg, sizes)?;
+    let rc_path = ico_path.with_extension("rc");
+    let rc = rc::resource_script(&ico_path.to_string_lossy(), None);
+    // Leave an unchanged file alone so its timestamp does not trigger a resource recompile
+    if std::fs::read_to_string(&rc_path).ok().as_deref() != Some(rc.as_str()) {
+        std::fs::write(&rc_path, rc)?;
+    }
+    Ok(rc_path)
+}
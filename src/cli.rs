@@ -0,0 +1,431 @@
+use std::path::{Path, PathBuf};
+
+use rusty_svg2ico::lint::Severity;
+use rusty_svg2ico::{favicon, icns, lint, pe, ConvertOptions, EntryEncoding, IconSet, ResizeFilter, SizeOverride};
+
+const USAGE: &str = "\
+Usage:
+  Rusty_SVG2ICO convert <input.svg|input.png|...> [-o <output.ico|output.icns|output.cur>] [--hotspot X,Y]
+                        [--override SIZE=<file.svg|file.png>]... [render options]
+  Rusty_SVG2ICO batch <input dir> -o <output dir> [render options]
+  Rusty_SVG2ICO info <input.ico|input.icns|input.exe|input.dll>
+  Rusty_SVG2ICO extract <input.ico> [-o <output dir>]
+  Rusty_SVG2ICO lint <input.ico>
+  Rusty_SVG2ICO favicon <input.svg> -o <output dir> [--name <app name>] [render options]
+  Rusty_SVG2ICO embed <input.ico> --into <app.exe|app.dll> [-o <output.exe>]
+
+Render options:
+  -s, --sizes 256,48,16      icon sizes to generate (1-256)
+  --padding <fraction>       margin around the artwork, e.g. 0.1 for 10% per side
+  --background <RRGGBB[AA]>  fill color behind the artwork (default transparent)
+  --encoding <png|bmp|auto>  entry format; auto uses PNG for 256 px and BMP below
+  --dpi <dpi>                resolution for physical units in the SVG (default 96)
+  --no-antialias             render crisp, aliased shape edges
+  --filter <name>            filter for scaling PNG, JPEG, WebP, GIF, TIFF or BMP input:
+                             lanczos3 (default), catmull-rom, gaussian, triangle or nearest
+  --hotspot X,Y              cursor click point in pixels of the largest size (.cur only)
+  --override SIZE=<file>     use this SVG or exact-size PNG for one size instead of the input
+
+Run without arguments to start the graphical interface.";
+
+// Exit codes returned to the calling shell
+const EXIT_OK: i32 = 0;
+const EXIT_FAILURE: i32 = 1;
+const EXIT_USAGE: i32 = 2;
+
+enum Command {
+    Convert { input: PathBuf, output: PathBuf, options: ConvertOptions, hotspot: (u16, u16), overrides: Vec<(u16, PathBuf)> },
+    Batch { input_dir: PathBuf, output_dir: PathBuf, options: ConvertOptions },
+    Info { input: PathBuf },
+    Extract { input: PathBuf, output_dir: PathBuf },
+    Lint { input: PathBuf },
+    Favicon { input: PathBuf, output_dir: PathBuf, options: ConvertOptions, name: Option<String> },
+    Embed { input: PathBuf, exe: PathBuf, output: PathBuf },
+    Help,
+    Version,
+}
+
+/// Runs the headless command-line interface and returns the process exit code.
+pub fn run(args: &[String]) -> i32 {
+    attach_console();
+
+    let command = match parse(args) {
+        Ok(command) => command,
+        Err(err) => {
+            eprintln!("error: {}\n\n{}", err, USAGE);
+            return EXIT_USAGE;
+        }
+    };
+
+    let result = match command {
+        Command::Convert { input, output, options, hotspot, overrides } => convert(&input, &output, &options, hotspot, &overrides),
+        Command::Batch { input_dir, output_dir, options } => batch(&input_dir, &output_dir, &options),
+        Command::Info { input } => info(&input),
+        Command::Extract { input, output_dir } => extract(&input, &output_dir),
+        Command::Lint { input } => lint(&input),
+        Command::Favicon { input, output_dir, options, name } => favicon(&input, &output_dir, &options, name),
+        Command::Embed { input, exe, output } => embed(&input, &exe, &output),
+        Command::Help => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        Command::Version => {
+            println!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
+            Ok(())
+        }
+    };
+
+    match result {
+        Ok(()) => EXIT_OK,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            EXIT_FAILURE
+        }
+    }
+}
+
+fn parse(args: &[String]) -> Result<Command, String> {
+    let (name, rest) = args.split_first().ok_or("missing command")?;
+
+    let mut input = None;
+    let mut output = None;
+    let mut options = ConvertOptions::default();
+    let mut hotspot = (0, 0);
+    let mut app_name = None;
+    let mut overrides = Vec::new();
+    let mut exe = None;
+
+    let mut iter = rest.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "-o" | "--output" => {
+                let value = iter.next().ok_or(format!("{} requires a value", arg))?;
+                output = Some(PathBuf::from(value));
+            }
+            "-s" | "--sizes" => {
+                let value = iter.next().ok_or(format!("{} requires a value", arg))?;
+                options.sizes = rusty_svg2ico::parse_sizes(value).map_err(|err| err.to_string())?;
+            }
+            "--padding" => {
+                let value = iter.next().ok_or(format!("{} requires a value", arg))?;
+                options.padding = match value.parse::<f32>() {
+                    Ok(padding) if (0.0..0.5).contains(&padding) => padding,
+                    _ => return Err(format!("invalid padding '{}' (expected 0 to 0.5)", value)),
+                };
+            }
+            "--background" => {
+                let value = iter.next().ok_or(format!("{} requires a value", arg))?;
+                options.background = Some(rusty_svg2ico::parse_color(value).map_err(|err| err.to_string())?);
+            }
+            "--encoding" => {
+                let value = iter.next().ok_or(format!("{} requires a value", arg))?;
+                options.encoding = match value.as_str() {
+                    "png" => EntryEncoding::Png,
+                    "bmp" => EntryEncoding::Bmp,
+                    "auto" => EntryEncoding::Auto,
+                    _ => return Err(format!("invalid encoding '{}' (expected png, bmp or auto)", value)),
+                };
+            }
+            "--dpi" => {
+                let value = iter.next().ok_or(format!("{} requires a value", arg))?;
+                options.dpi = match value.parse::<f32>() {
+                    Ok(dpi) if dpi > 0.0 => dpi,
+                    _ => return Err(format!("invalid dpi '{}'", value)),
+                };
+            }
+            "--no-antialias" => options.anti_alias = false,
+            "--filter" => {
+                let value = iter.next().ok_or(format!("{} requires a value", arg))?;
+                options.filter = match value.as_str() {
+                    "lanczos3" => ResizeFilter::Lanczos3,
+                    "catmull-rom" => ResizeFilter::CatmullRom,
+                    "gaussian" => ResizeFilter::Gaussian,
+                    "triangle" => ResizeFilter::Triangle,
+                    "nearest" => ResizeFilter::Nearest,
+                    _ => return Err(format!(
+                        "invalid filter '{}' (expected lanczos3, catmull-rom, gaussian, triangle or nearest)",
+                        value
+                    )),
+                };
+            }
+            "--override" => {
+                let value = iter.next().ok_or(format!("{} requires a value", arg))?;
+                let parsed = value.split_once('=').and_then(|(size, path)| {
+                    let size = size.trim().parse::<u16>().ok().filter(|size| (1..=256).contains(size))?;
+                    Some((size, PathBuf::from(path)))
+                });
+                overrides.push(parsed.ok_or(format!("invalid override '{}' (expected SIZE=FILE)", value))?);
+            }
+            "--into" => {
+                let value = iter.next().ok_or(format!("{} requires a value", arg))?;
+                exe = Some(PathBuf::from(value));
+            }
+            "--name" => {
+                let value = iter.next().ok_or(format!("{} requires a value", arg))?;
+                app_name = Some(value.clone());
+            }
+            "--hotspot" => {
+                let value = iter.next().ok_or(format!("{} requires a value", arg))?;
+                let parsed = value
+                    .split_once(',')
+                    .and_then(|(x, y)| Some((x.trim().parse().ok()?, y.trim().parse().ok()?)));
+                hotspot = parsed.ok_or(format!("invalid hotspot '{}' (expected X,Y)", value))?;
+            }
+            flag if flag.starts_with('-') => return Err(format!("unknown option '{}'", flag)),
+            path if input.is_none() => input = Some(PathBuf::from(path)),
+            extra => return Err(format!("unexpected argument '{}'", extra)),
+        }
+    }
+
+    match name.as_str() {
+        "convert" => {
+            let input = input.ok_or("convert requires an input SVG or image file")?;
+            let output = output.unwrap_or_else(|| input.with_extension("ico"));
+            Ok(Command::Convert { input, output, options, hotspot, overrides })
+        }
+        "batch" => {
+            let input_dir = input.ok_or("batch requires an input directory")?;
+            let output_dir = output.ok_or("batch requires an output directory (-o)")?;
+            Ok(Command::Batch { input_dir, output_dir, options })
+        }
+        "info" => {
+            let input = input.ok_or("info requires an input ICO file")?;
+            Ok(Command::Info { input })
+        }
+        "extract" => {
+            let input = input.ok_or("extract requires an input ICO file")?;
+            let output_dir = output.unwrap_or_else(|| PathBuf::from("."));
+            Ok(Command::Extract { input, output_dir })
+        }
+        "lint" => {
+            let input = input.ok_or("lint requires an input ICO file")?;
+            Ok(Command::Lint { input })
+        }
+        "favicon" => {
+            let input = input.ok_or("favicon requires an input SVG file")?;
+            let output_dir = output.ok_or("favicon requires an output directory (-o)")?;
+            Ok(Command::Favicon { input, output_dir, options, name: app_name })
+        }
+        "embed" => {
+            let input = input.ok_or("embed requires an input ICO file")?;
+            let exe = exe.ok_or("embed requires the executable to put the icon into (--into)")?;
+            // Never overwrite the original unless asked to: app.exe becomes app-icon.exe
+            let output = output.unwrap_or_else(|| {
+                let stem = exe.file_stem().unwrap_or_default().to_string_lossy();
+                let name = match exe.extension() {
+                    Some(extension) => format!("{}-icon.{}", stem, extension.to_string_lossy()),
+                    None => format!("{}-icon", stem),
+                };
+                exe.with_file_name(name)
+            });
+            Ok(Command::Embed { input, exe, output })
+        }
+        "help" | "-h" | "--help" => Ok(Command::Help),
+        "version" | "-V" | "--version" => Ok(Command::Version),
+        other => Err(format!("unknown command '{}'", other)),
+    }
+}
+
+fn has_extension(path: &Path, extension: &str) -> bool {
+    path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case(extension))
+}
+
+fn is_icns(path: &Path) -> bool {
+    has_extension(path, "icns")
+}
+
+// The output extension picks the format: .icns writes a macOS icon, .cur a cursor, anything else an ICO.
+// Raster inputs are scaled instead of rendered, with a warning for sizes larger than the source.
+fn convert(
+    input: &Path,
+    output: &Path,
+    options: &ConvertOptions,
+    hotspot: (u16, u16),
+    overrides: &[(u16, PathBuf)],
+) -> Result<(), String> {
+    let overrides = overrides
+        .iter()
+        .map(|(size, path)| SizeOverride::from_file(*size, path).map_err(|err| format!("{}: {}", path.display(), err)))
+        .collect::<Result<Vec<_>, _>>()?;
+    let is_raster = rusty_svg2ico::is_raster_path(input);
+    if is_raster && is_icns(output) {
+        return Err(format!("{}: ICNS output needs an SVG input", input.display()));
+    }
+    let data = std::fs::read(input)
+        .map_err(rusty_svg2ico::Error::from)
+        .and_then(|source| {
+            if is_icns(output) {
+                return icns::svg_to_icns(&source, options);
+            }
+            let icon_set = if is_raster {
+                if let Some(warning) = rusty_svg2ico::upscale_warning(&source, options, &overrides)? {
+                    eprintln!("warning: {}: {}", input.display(), warning);
+                }
+                rusty_svg2ico::convert_raster_with_overrides(&source, options, &overrides)?
+            } else {
+                rusty_svg2ico::convert_svg_with_overrides(&source, options, &overrides)?
+            };
+            if has_extension(output, "cur") {
+                let largest = (0..icon_set.len()).max_by_key(|&index| icon_set.entries()[index].width()).unwrap_or(0);
+                icon_set.to_cur_bytes(&icon_set.scaled_hotspots(largest, hotspot))
+            } else {
+                icon_set.to_ico_bytes()
+            }
+        })
+        .map_err(|err| format!("{}: {}", input.display(), err))?;
+    std::fs::write(output, data).map_err(|err| format!("{}: {}", output.display(), err))
+}
+
+fn batch(input_dir: &Path, output_dir: &Path, options: &ConvertOptions) -> Result<(), String> {
+    let items = rusty_svg2ico::batch::convert_dir(input_dir, output_dir, options)
+        .map_err(|err| format!("{}: {}", input_dir.display(), err))?;
+
+    let mut failed = 0;
+    for item in &items {
+        if let Err(err) = &item.result {
+            eprintln!("failed: {}: {}", item.source.display(), err);
+            failed += 1;
+        }
+    }
+    println!("{} converted, {} failed", items.len() - failed, failed);
+
+    if failed > 0 {
+        return Err(format!("{} of {} files failed to convert", failed, items.len()));
+    }
+    Ok(())
+}
+
+fn read_icon_set(input: &Path) -> Result<IconSet, String> {
+    std::fs::read(input)
+        .map_err(rusty_svg2ico::Error::from)
+        .and_then(|data| IconSet::from_ico_bytes(&data))
+        .map_err(|err| format!("{}: {}", input.display(), err))
+}
+
+fn info(input: &Path) -> Result<(), String> {
+    if is_icns(input) {
+        return icns_info(input);
+    }
+    if pe::PE_EXTENSIONS.iter().any(|extension| has_extension(input, extension)) {
+        return pe_info(input);
+    }
+    let icon_set = read_icon_set(input)?;
+    println!("{}: {} entries", input.display(), icon_set.len());
+    print_entries(&icon_set, "  ");
+    Ok(())
+}
+
+// Cursor directories hold the hotspot where icons have the bit depth, so show that instead
+fn print_entries(icon_set: &IconSet, indent: &str) {
+    for entry in icon_set.entries() {
+        let detail = match entry.cursor_hotspot() {
+            Some((x, y)) if icon_set.is_cursor() => format!("hotspot {},{}", x, y),
+            _ => format!("{} bpp", entry.bits_per_pixel()),
+        };
+        println!(
+            "{}{} x {}  {}  {}  {} bytes",
+            indent,
+            entry.width(),
+            entry.height(),
+            detail,
+            if entry.is_png() { "PNG" } else { "BMP" },
+            entry.data().len()
+        );
+    }
+}
+
+// Lists every icon group embedded in an EXE or DLL
+fn pe_info(input: &Path) -> Result<(), String> {
+    let groups = std::fs::read(input)
+        .map_err(rusty_svg2ico::Error::from)
+        .and_then(|data| pe::read_icon_groups(&data))
+        .map_err(|err| format!("{}: {}", input.display(), err))?;
+    println!("{}: {} icon groups", input.display(), groups.len());
+    for group in &groups {
+        let icon_set = IconSet::from_ico_bytes(&group.ico).map_err(|err| format!("{}: {}: {}", input.display(), group.name, err))?;
+        println!("  {} (language {}): {} entries", group.name, group.language, icon_set.len());
+        print_entries(&icon_set, "    ");
+    }
+    Ok(())
+}
+
+fn icns_info(input: &Path) -> Result<(), String> {
+    let entries = std::fs::read(input)
+        .map_err(rusty_svg2ico::Error::from)
+        .and_then(|data| icns::read_icns(&data))
+        .map_err(|err| format!("{}: {}", input.display(), err))?;
+    println!("{}: {} images", input.display(), entries.len());
+    for entry in &entries {
+        println!("  {} x {}  {}", entry.image.width(), entry.image.height(), entry.kind);
+    }
+    Ok(())
+}
+
+fn extract(input: &Path, output_dir: &Path) -> Result<(), String> {
+    let icon_set = read_icon_set(input)?;
+    let stem = input.file_stem().and_then(|stem| stem.to_str()).unwrap_or("icon");
+    icon_set
+        .write_pngs(output_dir, stem)
+        .map(|_| ())
+        .map_err(|err| format!("{}: {}", output_dir.display(), err))
+}
+
+// Prints every finding; only error-level findings fail the command
+fn lint(input: &Path) -> Result<(), String> {
+    let findings = std::fs::read(input)
+        .map_err(rusty_svg2ico::Error::from)
+        .and_then(|data| lint::lint_ico(&data))
+        .map_err(|err| format!("{}: {}", input.display(), err))?;
+    for finding in &findings {
+        println!("{}", finding);
+    }
+    let count = |severity| findings.iter().filter(|finding| finding.severity == severity).count();
+    let errors = count(Severity::Error);
+    println!("{}: {} errors, {} warnings, {} notes", input.display(), errors, count(Severity::Warning), count(Severity::Info));
+
+    if errors > 0 {
+        return Err(format!("{}: {} compatibility errors", input.display(), errors));
+    }
+    Ok(())
+}
+
+// Writes the web favicon kit and prints the <link> tags to paste into the page
+fn favicon(input: &Path, output_dir: &Path, options: &ConvertOptions, name: Option<String>) -> Result<(), String> {
+    let name = name.unwrap_or_else(|| input.file_stem().map_or_else(String::new, |stem| stem.to_string_lossy().into_owned()));
+    let bundle = std::fs::read(input)
+        .map_err(rusty_svg2ico::Error::from)
+        .and_then(|svg| favicon::favicon_bundle(&svg, options, &name))
+        .map_err(|err| format!("{}: {}", input.display(), err))?;
+    bundle.write_to(output_dir).map_err(|err| format!("{}: {}", output_dir.display(), err))?;
+    print!("{}", bundle.html);
+    Ok(())
+}
+
+// Replaces the application icon of an EXE or DLL; the file is only written once the new icon reads back
+fn embed(input: &Path, exe: &Path, output: &Path) -> Result<(), String> {
+    let ico = std::fs::read(input).map_err(|err| format!("{}: {}", input.display(), err))?;
+    let patched = std::fs::read(exe)
+        .map_err(rusty_svg2ico::Error::from)
+        .and_then(|data| pe::replace_icon(&data, &ico))
+        .map_err(|err| format!("{}: {}", exe.display(), err))?;
+    std::fs::write(output, patched).map_err(|err| format!("{}: {}", output.display(), err))
+}
+
+// Release builds use the Windows GUI subsystem, which starts without a console.
+// Attach to the parent's console so output and errors reach the calling shell.
+#[cfg(windows)]
+fn attach_console() {
+    const ATTACH_PARENT_PROCESS: u32 = u32::MAX;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn AttachConsole(process_id: u32) -> i32;
+    }
+
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
+#[cfg(not(windows))]
+fn attach_console() {}
@@ -0,0 +1,80 @@
+use std::fmt;
+use std::io;
+
+/// Errors returned by the conversion and ICO handling functions.
+#[derive(Debug)]
+pub enum Error {
+    /// Reading or writing a file failed.
+    Io(io::Error),
+    /// The SVG could not be parsed.
+    InvalidSvg(String),
+    /// Encoding or decoding a raster image (PNG and friends) failed.
+    Image(image::ImageError),
+    /// The data is not a valid ICO file, or one of its entries is corrupt.
+    InvalidIco(io::Error),
+    /// The data is not a valid ICNS file.
+    InvalidIcns(String),
+    /// The data is not a valid Windows executable or DLL, or its resources are corrupt.
+    InvalidPe(String),
+    /// A `Cargo.toml` could not be parsed or has no `[package]` section.
+    InvalidManifest(String),
+    /// An icon size outside the 1-256 range ICO supports was requested.
+    InvalidSize(u32),
+    /// A size list contained something that is not a number from 1 to 256.
+    ParseSize(String),
+    /// A color was not written as `RRGGBB` or `RRGGBBAA` hex.
+    ParseColor(String),
+    /// Cursor hotspots did not match the entries they belong to.
+    InvalidHotspot(String),
+    /// A PNG size override is not exactly the size it replaces.
+    OverrideSize { size: u16, width: u32, height: u32 },
+    /// The conversion was asked to produce no sizes at all.
+    NoSizes,
+    /// An entry index past the end of an icon set.
+    InvalidIndex { index: usize, len: usize },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "{}", err),
+            Error::Image(err) => write!(f, "{}", err),
+            Error::InvalidSvg(msg) => write!(f, "invalid SVG: {}", msg),
+            Error::InvalidIco(err) => write!(f, "invalid ICO data: {}", err),
+            Error::InvalidIcns(msg) => write!(f, "invalid ICNS data: {}", msg),
+            Error::InvalidPe(msg) => write!(f, "invalid executable: {}", msg),
+            Error::InvalidManifest(msg) => write!(f, "invalid Cargo.toml: {}", msg),
+            Error::InvalidSize(size) => write!(f, "invalid icon size {} (expected 1-256)", size),
+            Error::ParseSize(text) => write!(f, "'{}' is not a valid icon size (expected 1-256)", text),
+            Error::ParseColor(text) => write!(f, "invalid color '{}' (expected RRGGBB or RRGGBBAA)", text),
+            Error::InvalidHotspot(msg) => write!(f, "invalid cursor hotspot: {}", msg),
+            Error::OverrideSize { size, width, height } => {
+                write!(f, "the {} px override image is {} x {} (expected {} x {})", size, width, height, size, size)
+            }
+            Error::NoSizes => write!(f, "no icon sizes requested"),
+            Error::InvalidIndex { index, len } => write!(f, "entry {} does not exist (the icon has {} entries)", index, len),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(err) | Error::InvalidIco(err) => Some(err),
+            Error::Image(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<image::ImageError> for Error {
+    fn from(err: image::ImageError) -> Self {
+        Error::Image(err)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
@@ -0,0 +1,105 @@
+//! Favicon kit for web projects: ICO, PNG touch/app icons, SVG, web manifest and HTML snippet.
+
+use std::path::Path;
+
+use crate::render::encode_png;
+use crate::{ConvertOptions, EntryEncoding, Error, IconEntry, IconSet, SvgRenderer};
+
+/// Sizes packed into `favicon.ico`, the ones browsers and Windows still ask for.
+pub const FAVICON_ICO_SIZES: &[u16] = &[48, 32, 16];
+
+/// Side of `apple-touch-icon.png`, as recommended for current iOS devices.
+pub const APPLE_TOUCH_SIZE: u32 = 180;
+
+/// The generated files of a favicon kit, ready to be written into a web root.
+pub struct FaviconBundle {
+    /// `(file name, contents)` pairs.
+    pub files: Vec<(String, Vec<u8>)>,
+    /// `<link>` tags to paste into the page `<head>`; also included in `files` as `favicon.html`.
+    pub html: String,
+}
+
+impl FaviconBundle {
+    /// Writes every file into `dir`, creating it if needed.
+    pub fn write_to(&self, dir: &Path) -> Result<(), Error> {
+        std::fs::create_dir_all(dir)?;
+        for (name, data) in &self.files {
+            std::fs::write(dir.join(name), data)?;
+        }
+        Ok(())
+    }
+}
+
+/// Builds a complete favicon kit from an SVG.
+///
+/// `app_name` goes into `site.webmanifest`. The size list in `options` is ignored; every file has
+/// its own fixed sizes. iOS shows transparency as black, so the Apple touch icon is rendered on
+/// `options.background`, or white if none is set.
+pub fn favicon_bundle(svg: &[u8], options: &ConvertOptions, app_name: &str) -> Result<FaviconBundle, Error> {
+    let renderer = SvgRenderer::new(svg, options)?;
+
+    let mut favicon = IconSet::default();
+    for &size in FAVICON_ICO_SIZES {
+        favicon.push(IconEntry::from_rgba(&renderer.render(size.into())?, EntryEncoding::Png)?)?;
+    }
+
+    let opaque_options = ConvertOptions {
+        background: Some(options.background.unwrap_or([255, 255, 255, 255])),
+        ..options.clone()
+    };
+    let apple_touch = SvgRenderer::new(svg, &opaque_options)?.render(APPLE_TOUCH_SIZE)?;
+
+    // Keep the original when rewriting it does not make it smaller (e.g. lots of text turned into paths)
+    let optimized = renderer.to_optimized_svg().into_bytes();
+    let favicon_svg = if optimized.len() < svg.len() { optimized } else { svg.to_vec() };
+
+    let html = [
+        r#"<link rel="icon" href="/favicon.ico" sizes="32x32">"#,
+        r#"<link rel="icon" href="/favicon.svg" type="image/svg+xml">"#,
+        r#"<link rel="apple-touch-icon" href="/apple-touch-icon.png">"#,
+        r#"<link rel="manifest" href="/site.webmanifest">"#,
+    ]
+    .join("\n")
+        + "\n";
+
+    let files = vec![
+        ("favicon.ico".to_string(), favicon.to_ico_bytes()?),
+        ("favicon.svg".to_string(), favicon_svg),
+        ("apple-touch-icon.png".to_string(), encode_png(&apple_touch)?),
+        ("icon-192.png".to_string(), encode_png(&renderer.render(192)?)?),
+        ("icon-512.png".to_string(), encode_png(&renderer.render(512)?)?),
+        ("site.webmanifest".to_string(), web_manifest(app_name).into_bytes()),
+        ("favicon.html".to_string(), html.clone().into_bytes()),
+    ];
+    Ok(FaviconBundle { files, html })
+}
+
+fn web_manifest(app_name: &str) -> String {
+    let name = json_string(app_name);
+    format!(
+        r#"{{
+  "name": {name},
+  "short_name": {name},
+  "icons": [
+    {{ "src": "/icon-192.png", "type": "image/png", "sizes": "192x192" }},
+    {{ "src": "/icon-512.png", "type": "image/png", "sizes": "512x512" }}
+  ]
+}}
+"#
+    )
+}
+
+fn json_string(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
@@ -0,0 +1,310 @@
+//! Reading and writing Apple `.icns` icon files.
+
+use std::collections::hash_map::{Entry, HashMap};
+
+use crate::bytes::PNG_SIGNATURE;
+use crate::render::encode_png;
+use crate::{ConvertOptions, Error, SvgRenderer};
+
+/// Chunks written by [`svg_to_icns`], with the pixel size each one holds.
+///
+/// `ic04`/`ic05` are stored as PackBits-compressed ARGB like `iconutil` does; the rest are PNG.
+/// The `ic11`-`ic14` chunks and `ic10` are the @2x (Retina) variants of the 16-512 pt sizes.
+pub const ICNS_TYPES: &[(&[u8; 4], u32)] = &[
+    (b"ic04", 16),
+    (b"ic05", 32),
+    (b"ic11", 32),
+    (b"ic12", 64),
+    (b"ic07", 128),
+    (b"ic13", 256),
+    (b"ic08", 256),
+    (b"ic14", 512),
+    (b"ic09", 512),
+    (b"ic10", 1024),
+];
+
+/// One decoded image from an ICNS file.
+#[derive(Debug, Clone)]
+pub struct IcnsEntry {
+    /// The four-character chunk type, e.g. `ic08`.
+    pub kind: String,
+    pub image: image::RgbaImage,
+}
+
+/// Renders an SVG at every ICNS size, including the @2x variants, and assembles the ICNS file.
+///
+/// The size list in `options` is ignored; ICNS has a fixed set of sizes.
+pub fn svg_to_icns(svg: &[u8], options: &ConvertOptions) -> Result<Vec<u8>, Error> {
+    let renderer = SvgRenderer::new(svg, options)?;
+    let mut rendered: HashMap<u32, image::RgbaImage> = HashMap::new();
+    let mut chunks = Vec::with_capacity(ICNS_TYPES.len());
+    for &(kind, size) in ICNS_TYPES {
+        let image = match rendered.entry(size) {
+            Entry::Occupied(slot) => slot.into_mut(),
+            Entry::Vacant(slot) => slot.insert(renderer.render(size)?),
+        };
+        chunks.push((*kind, encode_chunk(kind, image)?));
+    }
+    Ok(write_icns(&chunks))
+}
+
+fn encode_chunk(kind: &[u8; 4], image: &image::RgbaImage) -> Result<Vec<u8>, Error> {
+    if kind == b"ic04" || kind == b"ic05" {
+        return Ok(encode_argb(image));
+    }
+    encode_png(image)
+}
+
+/// Assembles an ICNS file from `(type, data)` chunks.
+pub fn write_icns(chunks: &[([u8; 4], Vec<u8>)]) -> Vec<u8> {
+    let total: usize = 8 + chunks.iter().map(|(_, data)| 8 + data.len()).sum::<usize>();
+    let mut out = Vec::with_capacity(total);
+    out.extend_from_slice(b"icns");
+    out.extend_from_slice(&(total as u32).to_be_bytes());
+    for (kind, data) in chunks {
+        out.extend_from_slice(kind);
+        out.extend_from_slice(&((8 + data.len()) as u32).to_be_bytes());
+        out.extend_from_slice(data);
+    }
+    out
+}
+
+/// Decodes every image in an ICNS file.
+///
+/// PNG, ARGB and the legacy RGB + mask chunks (`is32`/`s8mk` ... `it32`/`t8mk`) are supported.
+/// JPEG 2000 images and non-image chunks such as `TOC ` are skipped.
+pub fn read_icns(data: &[u8]) -> Result<Vec<IcnsEntry>, Error> {
+    if data.len() < 8 || &data[0..4] != b"icns" {
+        return Err(Error::InvalidIcns("missing 'icns' header".to_string()));
+    }
+    let declared = u32::from_be_bytes([data[4], data[5], data[6], data[7]]) as usize;
+    let data = &data[..declared.min(data.len())];
+
+    let mut chunks: Vec<([u8; 4], &[u8])> = Vec::new();
+    let mut offset = 8;
+    while offset + 8 <= data.len() {
+        let kind = [data[offset], data[offset + 1], data[offset + 2], data[offset + 3]];
+        let length = u32::from_be_bytes([data[offset + 4], data[offset + 5], data[offset + 6], data[offset + 7]]) as usize;
+        if length < 8 || offset + length > data.len() {
+            return Err(Error::InvalidIcns(format!(
+                "chunk '{}' at offset {} has invalid length {}",
+                String::from_utf8_lossy(&kind),
+                offset,
+                length
+            )));
+        }
+        chunks.push((kind, &data[offset + 8..offset + length]));
+        offset += length;
+    }
+
+    let mut entries = Vec::new();
+    for &(kind, body) in &chunks {
+        let image = if body.starts_with(PNG_SIGNATURE) {
+            image::load_from_memory_with_format(body, image::ImageFormat::Png)
+                .map_err(|err| Error::InvalidIcns(err.to_string()))?
+                .to_rgba8()
+        } else if body.starts_with(b"ARGB") {
+            match argb_size(&kind) {
+                Some(size) => decode_argb(&body[4..], size)?,
+                None => continue,
+            }
+        } else if let Some((size, mask_kind)) = legacy_rgb(&kind) {
+            let mask = chunks.iter().find(|(other, _)| other == mask_kind).map(|(_, mask)| *mask);
+            decode_legacy_rgb(&kind, body, size, mask)?
+        } else {
+            continue;
+        };
+        entries.push(IcnsEntry {
+            kind: String::from_utf8_lossy(&kind).into_owned(),
+            image,
+        });
+    }
+    Ok(entries)
+}
+
+fn argb_size(kind: &[u8; 4]) -> Option<u32> {
+    match kind {
+        b"ic04" => Some(16),
+        b"ic05" => Some(32),
+        b"icsb" => Some(18),
+        _ => None,
+    }
+}
+
+fn legacy_rgb(kind: &[u8; 4]) -> Option<(u32, &'static [u8; 4])> {
+    match kind {
+        b"is32" => Some((16, b"s8mk")),
+        b"il32" => Some((32, b"l8mk")),
+        b"ih32" => Some((48, b"h8mk")),
+        b"it32" => Some((128, b"t8mk")),
+        _ => None,
+    }
+}
+
+fn encode_argb(image: &image::RgbaImage) -> Vec<u8> {
+    let mut out = b"ARGB".to_vec();
+    for channel in [3, 0, 1, 2] {
+        let plane: Vec<u8> = image.pixels().map(|pixel| pixel.0[channel]).collect();
+        out.extend(pack_bits(&plane));
+    }
+    out
+}
+
+fn decode_argb(body: &[u8], size: u32) -> Result<image::RgbaImage, Error> {
+    let pixels = (size * size) as usize;
+    let planes = unpack_bits(body, pixels * 4)?;
+    let (alpha, rgb) = planes.split_at(pixels);
+    Ok(planes_to_image(size, rgb, Some(alpha)))
+}
+
+fn decode_legacy_rgb(kind: &[u8; 4], body: &[u8], size: u32, mask: Option<&[u8]>) -> Result<image::RgbaImage, Error> {
+    let pixels = (size * size) as usize;
+    // it32 data starts with four unused bytes
+    let body = if kind == b"it32" { body.get(4..).unwrap_or_default() } else { body };
+    // Uncompressed data is exactly three full planes; anything else is PackBits
+    let rgb = if body.len() == pixels * 3 { body.to_vec() } else { unpack_bits(body, pixels * 3)? };
+    let alpha = mask.filter(|mask| mask.len() == pixels);
+    Ok(planes_to_image(size, &rgb, alpha))
+}
+
+fn planes_to_image(size: u32, rgb: &[u8], alpha: Option<&[u8]>) -> image::RgbaImage {
+    let pixels = (size * size) as usize;
+    image::RgbaImage::from_fn(size, size, |x, y| {
+        let index = (y * size + x) as usize;
+        let a = alpha.map_or(255, |alpha| alpha[index]);
+        image::Rgba([rgb[index], rgb[pixels + index], rgb[2 * pixels + index], a])
+    })
+}
+
+// ICNS flavour of PackBits: a control byte below 0x80 copies the next control + 1 bytes,
+// 0x80 and above repeats the next byte control - 125 times (3 to 130).
+fn pack_bits(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut literal: Vec<u8> = Vec::new();
+    let mut index = 0;
+    while index < data.len() {
+        let byte = data[index];
+        let mut run = 1;
+        while index + run < data.len() && data[index + run] == byte && run < 130 {
+            run += 1;
+        }
+        if run >= 3 {
+            flush_literal(&mut out, &mut literal);
+            out.push((run + 125) as u8);
+            out.push(byte);
+            index += run;
+        } else {
+            literal.push(byte);
+            if literal.len() == 128 {
+                flush_literal(&mut out, &mut literal);
+            }
+            index += 1;
+        }
+    }
+    flush_literal(&mut out, &mut literal);
+    out
+}
+
+fn flush_literal(out: &mut Vec<u8>, literal: &mut Vec<u8>) {
+    if !literal.is_empty() {
+        out.push((literal.len() - 1) as u8);
+        out.append(literal);
+    }
+}
+
+fn unpack_bits(data: &[u8], expected: usize) -> Result<Vec<u8>, Error> {
+    let truncated = || Error::InvalidIcns("compressed image data is truncated".to_string());
+    let mut out = Vec::with_capacity(expected);
+    let mut index = 0;
+    while out.len() < expected {
+        let control = *data.get(index).ok_or_else(truncated)? as usize;
+        index += 1;
+        if control < 0x80 {
+            let literal = data.get(index..index + control + 1).ok_or_else(truncated)?;
+            out.extend_from_slice(literal);
+            index += control + 1;
+        } else {
+            let byte = *data.get(index).ok_or_else(truncated)?;
+            out.extend(std::iter::repeat_n(byte, control - 125));
+            index += 1;
+        }
+    }
+    out.truncate(expected);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(data: &[u8]) -> Vec<u8> {
+        let packed = pack_bits(data);
+        assert_eq!(unpack_bits(&packed, data.len()).unwrap(), data);
+        packed
+    }
+
+    #[test]
+    fn packs_runs() {
+        assert_eq!(round_trip(&[5; 3]), [128, 5]);
+        assert_eq!(round_trip(&[9; 130]), [255, 9]);
+        // A run longer than 130 continues in the next control byte
+        assert_eq!(round_trip(&[9; 131]), [255, 9, 0, 9]);
+        assert_eq!(round_trip(&[9; 133]), [255, 9, 128, 9]);
+    }
+
+    #[test]
+    fn packs_literals() {
+        // Runs of two are cheaper as part of a literal
+        assert_eq!(round_trip(&[1, 2, 2, 3]), [3, 1, 2, 2, 3]);
+        let literal: Vec<u8> = (0..128).collect();
+        let packed = round_trip(&literal);
+        assert_eq!(packed.len(), 129);
+        assert_eq!(packed[0], 127);
+        // One more byte starts a second literal
+        let literal: Vec<u8> = (0..129).collect();
+        let packed = round_trip(&literal);
+        assert_eq!((packed.len(), packed[0], packed[129]), (131, 127, 0));
+    }
+
+    #[test]
+    fn packs_mixed_data() {
+        let mut data: Vec<u8> = (0..200).map(|value| (value % 7) as u8).collect();
+        data.extend([4; 140]);
+        data.extend((0..50).map(|value| value as u8));
+        data.extend([0; 3]);
+        round_trip(&data);
+        assert_eq!(unpack_bits(&[], 0).unwrap(), []);
+    }
+
+    #[test]
+    fn rejects_truncated_packed_data() {
+        assert!(matches!(unpack_bits(&[3, 1, 2], 4), Err(Error::InvalidIcns(_))));
+        assert!(matches!(unpack_bits(&[200], 80), Err(Error::InvalidIcns(_))));
+        assert!(matches!(unpack_bits(&[128, 5], 4), Err(Error::InvalidIcns(_))));
+    }
+
+    #[test]
+    fn argb_round_trip() {
+        let image = image::RgbaImage::from_fn(16, 16, |x, y| image::Rgba([x as u8 * 16, y as u8 * 16, 200, if x < 8 { 255 } else { 0 }]));
+        let encoded = encode_argb(&image);
+        assert!(encoded.starts_with(b"ARGB"));
+        assert_eq!(decode_argb(&encoded[4..], 16).unwrap(), image);
+    }
+
+    #[test]
+    fn svg_to_icns_round_trip() {
+        let svg = br##"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 16 16"><rect x="2" y="2" width="12" height="12" fill="#3366cc"/></svg>"##;
+        let icns = svg_to_icns(svg, &ConvertOptions::default()).unwrap();
+        assert_eq!(&icns[..4], b"icns");
+        assert_eq!(u32::from_be_bytes([icns[4], icns[5], icns[6], icns[7]]) as usize, icns.len());
+
+        let entries = read_icns(&icns).unwrap();
+        let read: Vec<(&str, u32)> = entries.iter().map(|entry| (entry.kind.as_str(), entry.image.width())).collect();
+        let expected: Vec<(&str, u32)> =
+            ICNS_TYPES.iter().map(|(kind, size)| (std::str::from_utf8(*kind).unwrap(), *size)).collect();
+        assert_eq!(read, expected);
+        assert!(entries.iter().all(|entry| entry.image.width() == entry.image.height()));
+        // The middle of the square is opaque in every size, the ARGB ones included
+        assert!(entries.iter().all(|entry| entry.image.get_pixel(entry.image.width() / 2, entry.image.height() / 2).0[3] == 255));
+    }
+}
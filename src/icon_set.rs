@@ -0,0 +1,373 @@
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::Error;
+
+/// How an image is stored inside an ICO file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EntryEncoding {
+    /// PNG-compressed, the smallest files (Windows Vista and later).
+    #[default]
+    Png,
+    /// Uncompressed BMP/DIB with an AND mask, readable by every ICO consumer.
+    Bmp,
+    /// PNG for 256 px entries and BMP below, the layout older Windows tools expect.
+    Auto,
+}
+
+/// A single image inside an ICO file.
+#[derive(Clone)]
+pub struct IconEntry {
+    entry: ico::IconDirEntry,
+}
+
+impl IconEntry {
+    /// Encodes an RGBA image (1-256 px per side) as a new entry.
+    pub fn from_rgba(image: &image::RgbaImage, encoding: EntryEncoding) -> Result<IconEntry, Error> {
+        let (width, height) = image.dimensions();
+        if !(1..=256).contains(&width) || !(1..=256).contains(&height) {
+            return Err(Error::InvalidSize(width.max(height)));
+        }
+        let icon_image = ico::IconImage::from_rgba_data(width, height, image.as_raw().clone());
+        let use_png = match encoding {
+            EntryEncoding::Png => true,
+            EntryEncoding::Bmp => false,
+            EntryEncoding::Auto => width >= 256 || height >= 256,
+        };
+        let entry = if use_png {
+            ico::IconDirEntry::encode_as_png(&icon_image)?
+        } else {
+            ico::IconDirEntry::encode_as_bmp(&icon_image)?
+        };
+        Ok(IconEntry { entry })
+    }
+
+    pub fn width(&self) -> u32 {
+        self.entry.width()
+    }
+
+    pub fn height(&self) -> u32 {
+        self.entry.height()
+    }
+
+    pub fn bits_per_pixel(&self) -> u16 {
+        self.entry.bits_per_pixel()
+    }
+
+    /// Returns true if the entry is stored as PNG rather than BMP.
+    pub fn is_png(&self) -> bool {
+        self.entry.is_png()
+    }
+
+    /// The click point of a cursor entry, in the entry's own pixels; `None` for icons.
+    pub fn cursor_hotspot(&self) -> Option<(u16, u16)> {
+        self.entry.cursor_hotspot()
+    }
+
+    /// The encoded image data exactly as stored in the ICO file.
+    pub fn data(&self) -> &[u8] {
+        self.entry.data()
+    }
+
+    /// Decodes the entry to RGBA pixels. BMP entries of any bit depth (1, 4, 8, 16, 24 or 32)
+    /// have their palette and AND transparency mask applied.
+    pub fn to_rgba(&self) -> Result<image::RgbaImage, Error> {
+        let image = self.entry.decode().map_err(Error::InvalidIco)?;
+        image::RgbaImage::from_raw(image.width(), image.height(), image.rgba_data().to_vec()).ok_or_else(|| {
+            Error::InvalidIco(io::Error::new(io::ErrorKind::InvalidData, "decoded pixel data has the wrong length"))
+        })
+    }
+
+    // Re-encodes the entry as a cursor image with `hotspot`, or as an icon image for `None`, since
+    // an ICO and a CUR directory cannot hold each other's entries. PNG/BMP storage is kept.
+    fn with_hotspot(self, hotspot: Option<(u16, u16)>) -> Result<IconEntry, Error> {
+        if self.cursor_hotspot() == hotspot {
+            return Ok(self);
+        }
+        let mut image = self.entry.decode().map_err(Error::InvalidIco)?;
+        image.set_cursor_hotspot(hotspot);
+        let entry = if self.is_png() {
+            ico::IconDirEntry::encode_as_png(&image)?
+        } else {
+            ico::IconDirEntry::encode_as_bmp(&image)?
+        };
+        Ok(IconEntry { entry })
+    }
+
+    /// Returns the entry as a PNG file, re-encoding BMP entries.
+    pub fn to_png_bytes(&self) -> Result<Vec<u8>, Error> {
+        if self.is_png() {
+            return Ok(self.data().to_vec());
+        }
+        let image = self.entry.decode().map_err(Error::InvalidIco)?;
+        let mut png = Vec::new();
+        image.write_png(&mut png).map_err(Error::InvalidIco)?;
+        Ok(png)
+    }
+}
+
+/// The contents of an ICO file: an ordered list of entries at different sizes.
+#[derive(Clone)]
+pub struct IconSet {
+    resource_type: ico::ResourceType,
+    entries: Vec<IconEntry>,
+}
+
+impl Default for IconSet {
+    fn default() -> Self {
+        IconSet { resource_type: ico::ResourceType::Icon, entries: Vec::new() }
+    }
+}
+
+impl IconSet {
+    /// Parses an ICO file.
+    pub fn from_ico_bytes(data: &[u8]) -> Result<IconSet, Error> {
+        let icon_dir = ico::IconDir::read(io::Cursor::new(data)).map_err(Error::InvalidIco)?;
+        let entries = icon_dir
+            .entries()
+            .iter()
+            .map(|entry| IconEntry { entry: entry.clone() })
+            .collect();
+        Ok(IconSet { resource_type: icon_dir.resource_type(), entries })
+    }
+
+    /// Serializes the entries, in order, into an ICO (or CUR) file.
+    pub fn to_ico_bytes(&self) -> Result<Vec<u8>, Error> {
+        let mut icon_dir = ico::IconDir::new(self.resource_type);
+        for entry in &self.entries {
+            icon_dir.add_entry(entry.entry.clone());
+        }
+        let mut data = Vec::new();
+        icon_dir.write(&mut data)?;
+        Ok(data)
+    }
+
+    /// Serializes the entries as a Windows cursor (CUR) file.
+    ///
+    /// `hotspots` holds one click point per entry, in that entry's own pixel coordinates.
+    pub fn to_cur_bytes(&self, hotspots: &[(u16, u16)]) -> Result<Vec<u8>, Error> {
+        if hotspots.len() != self.entries.len() {
+            return Err(Error::InvalidHotspot(format!(
+                "{} hotspots given for {} entries",
+                hotspots.len(),
+                self.entries.len()
+            )));
+        }
+        let mut cursor_dir = ico::IconDir::new(ico::ResourceType::Cursor);
+        for (entry, &(x, y)) in self.entries.iter().zip(hotspots) {
+            if u32::from(x) >= entry.width() || u32::from(y) >= entry.height() {
+                return Err(Error::InvalidHotspot(format!(
+                    "({}, {}) is outside the {} x {} entry",
+                    x,
+                    y,
+                    entry.width(),
+                    entry.height()
+                )));
+            }
+            let mut image = entry.entry.decode().map_err(Error::InvalidIco)?;
+            image.set_cursor_hotspot(Some((x, y)));
+            // Keep each entry's existing PNG/BMP storage
+            let cursor_entry = if entry.is_png() {
+                ico::IconDirEntry::encode_as_png(&image)?
+            } else {
+                ico::IconDirEntry::encode_as_bmp(&image)?
+            };
+            cursor_dir.add_entry(cursor_entry);
+        }
+        let mut data = Vec::new();
+        cursor_dir.write(&mut data)?;
+        Ok(data)
+    }
+
+    /// Scales a hotspot picked on entry `from` to every entry, keeping it on the same spot of the artwork.
+    pub fn scaled_hotspots(&self, from: usize, hotspot: (u16, u16)) -> Vec<(u16, u16)> {
+        let Some(source) = self.entries.get(from) else {
+            return vec![(0, 0); self.entries.len()];
+        };
+        // Work from the pixel centre so the hotspot lands in the matching pixel at every size
+        let fx = (f32::from(hotspot.0) + 0.5) / source.width() as f32;
+        let fy = (f32::from(hotspot.1) + 0.5) / source.height() as f32;
+        self.entries
+            .iter()
+            .map(|entry| {
+                let x = (fx * entry.width() as f32).floor().min((entry.width() - 1) as f32);
+                let y = (fy * entry.height() as f32).floor().min((entry.height() - 1) as f32);
+                (x as u16, y as u16)
+            })
+            .collect()
+    }
+
+    /// File name for entry `index` when extracted as PNG: `stem_WxH.png`, or `stem_WxH_Nbpp.png`
+    /// when another entry has the same size.
+    pub fn png_file_name(&self, index: usize, stem: &str) -> Result<String, Error> {
+        let entry = self.entries.get(index).ok_or_else(|| self.invalid_index(index))?;
+        let same_size = self
+            .entries
+            .iter()
+            .filter(|other| other.width() == entry.width() && other.height() == entry.height())
+            .count();
+        Ok(if same_size > 1 {
+            format!("{}_{}x{}_{}bpp.png", stem, entry.width(), entry.height(), entry.bits_per_pixel())
+        } else {
+            format!("{}_{}x{}.png", stem, entry.width(), entry.height())
+        })
+    }
+
+    /// Writes every entry into `dir` as a PNG named by [`IconSet::png_file_name`], creating the
+    /// directory if needed, and returns the written paths.
+    pub fn write_pngs(&self, dir: &Path, stem: &str) -> Result<Vec<PathBuf>, Error> {
+        std::fs::create_dir_all(dir)?;
+        let mut paths = Vec::with_capacity(self.entries.len());
+        for (index, entry) in self.entries.iter().enumerate() {
+            let path = dir.join(self.png_file_name(index, stem)?);
+            std::fs::write(&path, entry.to_png_bytes()?)?;
+            paths.push(path);
+        }
+        Ok(paths)
+    }
+
+    /// Returns true if the set was read from a cursor (CUR) file.
+    pub fn is_cursor(&self) -> bool {
+        self.resource_type == ico::ResourceType::Cursor
+    }
+
+    fn invalid_index(&self, index: usize) -> Error {
+        Error::InvalidIndex { index, len: self.entries.len() }
+    }
+
+    // Makes `entry` the same kind as the set: icon entries added to a cursor get `hotspot`, and
+    // cursor entries added to an icon lose theirs
+    fn conform(&self, entry: IconEntry, hotspot: (u16, u16)) -> Result<IconEntry, Error> {
+        if !self.is_cursor() {
+            return entry.with_hotspot(None);
+        }
+        match entry.cursor_hotspot() {
+            Some(_) => Ok(entry),
+            None => entry.with_hotspot(Some(hotspot)),
+        }
+    }
+
+    /// Appends an entry; ICO entries are conventionally ordered largest first. In a cursor set an
+    /// icon entry becomes a cursor entry with its hotspot at the top-left pixel.
+    pub fn push(&mut self, entry: IconEntry) -> Result<(), Error> {
+        let entry = self.conform(entry, (0, 0))?;
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    /// Inserts an entry at `index`, shifting later entries down. Cursor sets convert it like [`IconSet::push`].
+    /// Fails if `index > len`.
+    pub fn insert(&mut self, index: usize, entry: IconEntry) -> Result<(), Error> {
+        if index > self.entries.len() {
+            return Err(self.invalid_index(index));
+        }
+        let entry = self.conform(entry, (0, 0))?;
+        self.entries.insert(index, entry);
+        Ok(())
+    }
+
+    /// Removes and returns the entry at `index`.
+    pub fn remove(&mut self, index: usize) -> Result<IconEntry, Error> {
+        if index >= self.entries.len() {
+            return Err(self.invalid_index(index));
+        }
+        Ok(self.entries.remove(index))
+    }
+
+    /// Swaps in a new entry at `index` and returns the old one. In a cursor set an icon entry
+    /// takes over the hotspot of the entry it replaces.
+    pub fn replace(&mut self, index: usize, entry: IconEntry) -> Result<IconEntry, Error> {
+        let old = self.entries.get(index).ok_or_else(|| self.invalid_index(index))?;
+        let hotspot = old.cursor_hotspot().unwrap_or((0, 0));
+        let entry = self.conform(entry, hotspot)?;
+        Ok(std::mem::replace(&mut self.entries[index], entry))
+    }
+
+    /// Moves the entry at `from` to position `to`, keeping the order of the others.
+    pub fn move_entry(&mut self, from: usize, to: usize) -> Result<(), Error> {
+        if let Some(index) = [from, to].into_iter().find(|&index| index >= self.entries.len()) {
+            return Err(self.invalid_index(index));
+        }
+        let entry = self.entries.remove(from);
+        self.entries.insert(to, entry);
+        Ok(())
+    }
+
+    pub fn entries(&self) -> &[IconEntry] {
+        &self.entries
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(size: u32, encoding: EntryEncoding) -> IconEntry {
+        IconEntry::from_rgba(&image::RgbaImage::from_pixel(size, size, image::Rgba([255, 0, 0, 255])), encoding).unwrap()
+    }
+
+    fn cursor_set() -> IconSet {
+        let mut icon_set = IconSet::default();
+        icon_set.push(entry(32, EntryEncoding::Bmp)).unwrap();
+        IconSet::from_ico_bytes(&icon_set.to_cur_bytes(&[(5, 7)]).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn converts_icon_entries_added_to_a_cursor() {
+        let mut cursor = cursor_set();
+        cursor.insert(0, entry(48, EntryEncoding::Png)).unwrap();
+        cursor.push(entry(16, EntryEncoding::Bmp)).unwrap();
+
+        let reloaded = IconSet::from_ico_bytes(&cursor.to_ico_bytes().unwrap()).unwrap();
+        assert!(reloaded.is_cursor());
+        let hotspots: Vec<_> = reloaded.entries().iter().map(IconEntry::cursor_hotspot).collect();
+        assert_eq!(hotspots, [Some((0, 0)), Some((5, 7)), Some((0, 0))]);
+        assert!(reloaded.entries()[0].is_png());
+        assert!(!reloaded.entries()[2].is_png());
+    }
+
+    #[test]
+    fn replacement_keeps_the_cursor_hotspot() {
+        let mut cursor = cursor_set();
+        cursor.replace(0, entry(32, EntryEncoding::Png)).unwrap();
+        assert_eq!(cursor.entries()[0].cursor_hotspot(), Some((5, 7)));
+        assert!(cursor.to_ico_bytes().is_ok());
+    }
+
+    #[test]
+    fn rejects_indexes_past_the_end() {
+        let mut icon_set = IconSet::default();
+        icon_set.push(entry(32, EntryEncoding::Png)).unwrap();
+        icon_set.push(entry(16, EntryEncoding::Png)).unwrap();
+
+        assert!(matches!(icon_set.insert(3, entry(8, EntryEncoding::Png)), Err(Error::InvalidIndex { index: 3, len: 2 })));
+        assert!(matches!(icon_set.replace(2, entry(8, EntryEncoding::Png)), Err(Error::InvalidIndex { index: 2, len: 2 })));
+        assert!(matches!(icon_set.move_entry(0, 2), Err(Error::InvalidIndex { index: 2, .. })));
+        assert!(matches!(icon_set.remove(2), Err(Error::InvalidIndex { .. })));
+        assert!(matches!(icon_set.png_file_name(2, "app"), Err(Error::InvalidIndex { .. })));
+        assert_eq!(icon_set.len(), 2);
+
+        icon_set.move_entry(0, 1).unwrap();
+        assert_eq!(icon_set.entries()[0].width(), 16);
+        assert_eq!(icon_set.png_file_name(1, "app").unwrap(), "app_32x32.png");
+        assert_eq!(icon_set.remove(0).unwrap().width(), 16);
+    }
+
+    #[test]
+    fn drops_hotspots_of_cursor_entries_added_to_an_icon() {
+        let cursor = cursor_set();
+        let mut icon_set = IconSet::default();
+        icon_set.push(cursor.entries()[0].clone()).unwrap();
+        let reloaded = IconSet::from_ico_bytes(&icon_set.to_ico_bytes().unwrap()).unwrap();
+        assert!(!reloaded.is_cursor());
+        assert_eq!(reloaded.entries()[0].cursor_hotspot(), None);
+    }
+}
@@ -0,0 +1,268 @@
+//! Low-level view of an ICO/CUR file: what each directory entry declares and what its image
+//! data actually contains.
+
+use std::io;
+
+use crate::bytes::{u16_le, u32_le, PNG_SIGNATURE};
+use crate::Error;
+
+const DIR_HEADER_LEN: usize = 6;
+const DIR_ENTRY_LEN: usize = 16;
+
+/// Header fields and decoded facts about one entry of an ICO or CUR file.
+#[derive(Debug, Clone)]
+pub struct EntryInfo {
+    /// Width from the directory entry; a stored 0 means 256.
+    pub declared_width: u32,
+    /// Height from the directory entry; a stored 0 means 256.
+    pub declared_height: u32,
+    /// Palette size from the directory entry; 0 for true-color images.
+    pub declared_colors: u8,
+    /// Bit depth from the directory entry. `None` for cursors, which store the hotspot there.
+    pub declared_bits_per_pixel: Option<u16>,
+    /// Length of the image data in bytes.
+    pub byte_size: u32,
+    /// Position of the image data from the start of the file.
+    pub data_offset: u32,
+    /// The image data, or `None` if `data_offset`/`byte_size` point outside the file.
+    pub image: Option<ImageInfo>,
+}
+
+/// What the image data of an entry contains, read from its PNG or BMP header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageInfo {
+    pub is_png: bool,
+    pub width: u32,
+    pub height: u32,
+    pub bits_per_pixel: u16,
+    /// Number of palette colors; 0 if the image has no palette.
+    pub palette_colors: u32,
+}
+
+impl EntryInfo {
+    /// Returns true if the directory entry's width and height match the image data.
+    pub fn dimensions_match(&self) -> bool {
+        self.image
+            .as_ref()
+            .is_some_and(|image| image.width == self.declared_width && image.height == self.declared_height)
+    }
+}
+
+/// Reads the directory of an ICO or CUR file and the header of every image in it.
+///
+/// Unlike [`IconSet::from_ico_bytes`](crate::IconSet::from_ico_bytes) this does not decode pixels
+/// and tolerates entries with bad offsets or unreadable headers, so broken files can be examined.
+pub fn inspect_ico(data: &[u8]) -> Result<Vec<EntryInfo>, Error> {
+    let invalid = |msg: &str| Error::InvalidIco(io::Error::new(io::ErrorKind::InvalidData, msg.to_string()));
+    if data.len() < DIR_HEADER_LEN || u16_le(data, 0) != 0 {
+        return Err(invalid("missing ICO header"));
+    }
+    let is_cursor = match u16_le(data, 2) {
+        1 => false,
+        2 => true,
+        _ => return Err(invalid("unknown resource type (expected icon or cursor)")),
+    };
+    let count = u16_le(data, 4) as usize;
+    if data.len() < DIR_HEADER_LEN + count * DIR_ENTRY_LEN {
+        return Err(invalid("directory is truncated"));
+    }
+
+    let mut entries = Vec::with_capacity(count);
+    for index in 0..count {
+        let entry = &data[DIR_HEADER_LEN + index * DIR_ENTRY_LEN..][..DIR_ENTRY_LEN];
+        let byte_size = u32_le(entry, 8);
+        let data_offset = u32_le(entry, 12);
+        let image = (data_offset as usize)
+            .checked_add(byte_size as usize)
+            .and_then(|end| data.get(data_offset as usize..end))
+            .and_then(image_info);
+        entries.push(EntryInfo {
+            declared_width: if entry[0] == 0 { 256 } else { entry[0].into() },
+            declared_height: if entry[1] == 0 { 256 } else { entry[1].into() },
+            declared_colors: entry[2],
+            declared_bits_per_pixel: (!is_cursor).then(|| u16_le(entry, 6)),
+            byte_size,
+            data_offset,
+            image,
+        });
+    }
+    Ok(entries)
+}
+
+fn image_info(data: &[u8]) -> Option<ImageInfo> {
+    if data.starts_with(PNG_SIGNATURE) {
+        png_info(data)
+    } else {
+        bmp_info(data)
+    }
+}
+
+fn png_info(data: &[u8]) -> Option<ImageInfo> {
+    // IHDR is always the first chunk
+    let ihdr = data.get(8..33)?;
+    if &ihdr[4..8] != b"IHDR" {
+        return None;
+    }
+    let bit_depth = u16::from(ihdr[16]);
+    let channels = match ihdr[17] {
+        0 | 3 => 1,
+        2 => 3,
+        4 => 2,
+        6 => 4,
+        _ => return None,
+    };
+    let mut palette_colors = 0;
+    let mut offset = 8;
+    while let Some(header) = data.get(offset..offset + 8) {
+        let length = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        match &header[4..8] {
+            b"PLTE" => {
+                palette_colors = (length / 3) as u32;
+                break;
+            }
+            b"IDAT" | b"IEND" => break,
+            _ => offset += 12 + length,
+        }
+    }
+    Some(ImageInfo {
+        is_png: true,
+        width: u32::from_be_bytes([ihdr[8], ihdr[9], ihdr[10], ihdr[11]]),
+        height: u32::from_be_bytes([ihdr[12], ihdr[13], ihdr[14], ihdr[15]]),
+        bits_per_pixel: bit_depth * channels,
+        palette_colors,
+    })
+}
+
+fn bmp_info(data: &[u8]) -> Option<ImageInfo> {
+    // BITMAPINFOHEADER without the file header; the height covers both the XOR and AND masks
+    let header = data.get(..40)?;
+    if u32_le(header, 0) < 40 {
+        return None;
+    }
+    let width = i32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+    let height = i32::from_le_bytes([header[8], header[9], header[10], header[11]]);
+    let bits_per_pixel = u16_le(header, 14);
+    let colors_used = u32_le(header, 32);
+    let palette_colors = match (colors_used, bits_per_pixel) {
+        (0, 1..=8) => 1 << bits_per_pixel,
+        (used, _) => used,
+    };
+    Some(ImageInfo {
+        is_png: false,
+        width: width.unsigned_abs(),
+        height: height.unsigned_abs() / 2,
+        bits_per_pixel,
+        palette_colors,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // An ICO (type 1) or CUR (type 2) file; each image is (width, height, planes/hotspot x, bpp/hotspot y, data)
+    fn ico(resource_type: u16, images: &[(u8, u8, u16, u16, &[u8])]) -> Vec<u8> {
+        let mut data = vec![0, 0];
+        data.extend_from_slice(&resource_type.to_le_bytes());
+        data.extend_from_slice(&(images.len() as u16).to_le_bytes());
+        let mut offset = DIR_HEADER_LEN + images.len() * DIR_ENTRY_LEN;
+        for &(width, height, planes, bits_per_pixel, image) in images {
+            data.extend_from_slice(&[width, height, 0, 0]);
+            data.extend_from_slice(&planes.to_le_bytes());
+            data.extend_from_slice(&bits_per_pixel.to_le_bytes());
+            data.extend_from_slice(&(image.len() as u32).to_le_bytes());
+            data.extend_from_slice(&(offset as u32).to_le_bytes());
+            offset += image.len();
+        }
+        for image in images {
+            data.extend_from_slice(image.4);
+        }
+        data
+    }
+
+    fn png_chunk(png: &mut Vec<u8>, kind: &[u8; 4], contents: &[u8]) {
+        png.extend_from_slice(&(contents.len() as u32).to_be_bytes());
+        png.extend_from_slice(kind);
+        png.extend_from_slice(contents);
+        // The CRC is not checked
+        png.extend_from_slice(&[0; 4]);
+    }
+
+    // A 24 x 20 8-bit indexed PNG with a 5-color palette behind a text chunk
+    fn indexed_png() -> Vec<u8> {
+        let mut png = PNG_SIGNATURE.to_vec();
+        let mut ihdr = Vec::new();
+        ihdr.extend_from_slice(&24u32.to_be_bytes());
+        ihdr.extend_from_slice(&20u32.to_be_bytes());
+        ihdr.extend_from_slice(&[8, 3, 0, 0, 0]);
+        png_chunk(&mut png, b"IHDR", &ihdr);
+        png_chunk(&mut png, b"tEXt", b"Comment\0test");
+        png_chunk(&mut png, b"PLTE", &[0; 15]);
+        png_chunk(&mut png, b"IDAT", &[]);
+        png_chunk(&mut png, b"IEND", &[]);
+        png
+    }
+
+    // A BITMAPINFOHEADER whose height counts the AND mask too
+    fn bmp_header(width: i32, height: i32, bits_per_pixel: u16, colors_used: u32) -> Vec<u8> {
+        let mut header = vec![0; 40];
+        header[..4].copy_from_slice(&40u32.to_le_bytes());
+        header[4..8].copy_from_slice(&width.to_le_bytes());
+        header[8..12].copy_from_slice(&height.to_le_bytes());
+        header[12..14].copy_from_slice(&1u16.to_le_bytes());
+        header[14..16].copy_from_slice(&bits_per_pixel.to_le_bytes());
+        header[32..36].copy_from_slice(&colors_used.to_le_bytes());
+        header
+    }
+
+    #[test]
+    fn reads_png_palette() {
+        let entries = inspect_ico(&ico(1, &[(24, 20, 1, 8, &indexed_png())])).unwrap();
+        let image = entries[0].image.as_ref().unwrap();
+        assert_eq!(*image, ImageInfo { is_png: true, width: 24, height: 20, bits_per_pixel: 8, palette_colors: 5 });
+        assert!(entries[0].dimensions_match());
+    }
+
+    #[test]
+    fn reads_bmp_with_mask_and_implicit_palette() {
+        let data = ico(1, &[(16, 16, 1, 4, &bmp_header(16, 32, 4, 0)), (0, 0, 1, 32, &bmp_header(256, 512, 32, 0))]);
+        let entries = inspect_ico(&data).unwrap();
+        let image = entries[0].image.as_ref().unwrap();
+        assert_eq!(*image, ImageInfo { is_png: false, width: 16, height: 16, bits_per_pixel: 4, palette_colors: 16 });
+        assert_eq!((entries[1].declared_width, entries[1].declared_height), (256, 256));
+        assert_eq!(entries[1].image.as_ref().unwrap().palette_colors, 0);
+        assert!(entries.iter().all(EntryInfo::dimensions_match));
+    }
+
+    #[test]
+    fn tolerates_entry_past_end_of_file() {
+        let mut data = ico(1, &[(16, 16, 1, 4, &bmp_header(16, 32, 4, 0))]);
+        // Move the offset past the end, then make the size overflow
+        data[DIR_HEADER_LEN + 12..DIR_HEADER_LEN + 16].copy_from_slice(&0x1000u32.to_le_bytes());
+        let entries = inspect_ico(&data).unwrap();
+        assert!(entries[0].image.is_none());
+        assert!(!entries[0].dimensions_match());
+        data[DIR_HEADER_LEN + 8..DIR_HEADER_LEN + 16].copy_from_slice(&[0xFF; 8]);
+        assert!(inspect_ico(&data).unwrap()[0].image.is_none());
+    }
+
+    #[test]
+    fn rejects_truncated_directory() {
+        let data = ico(1, &[(16, 16, 1, 4, &bmp_header(16, 32, 4, 0)), (32, 32, 1, 4, &bmp_header(32, 64, 4, 0))]);
+        for len in [0, DIR_HEADER_LEN - 1, DIR_HEADER_LEN + DIR_ENTRY_LEN + 4] {
+            assert!(matches!(inspect_ico(&data[..len]), Err(Error::InvalidIco(_))), "length {}", len);
+        }
+        let mut unknown = data.clone();
+        unknown[2] = 3;
+        assert!(matches!(inspect_ico(&unknown), Err(Error::InvalidIco(_))));
+    }
+
+    #[test]
+    fn cursor_entries_have_no_declared_bit_depth() {
+        let entries = inspect_ico(&ico(2, &[(24, 20, 3, 4, &indexed_png())])).unwrap();
+        assert_eq!(entries[0].declared_bits_per_pixel, None);
+        assert_eq!(entries[0].image.as_ref().unwrap().bits_per_pixel, 8);
+        let icon = inspect_ico(&ico(1, &[(24, 20, 1, 8, &indexed_png())])).unwrap();
+        assert_eq!(icon[0].declared_bits_per_pixel, Some(8));
+    }
+}
@@ -0,0 +1,241 @@
+//! SVG to ICO conversion and ICO parsing shared by the Rusty SVG2ICO GUI and command line.
+//!
+//! ```no_run
+//! use rusty_svg2ico::{convert_svg, ConvertOptions};
+//!
+//! let svg = std::fs::read("logo.svg")?;
+//! let icons = convert_svg(&svg, &ConvertOptions::default())?;
+//! std::fs::write("logo.ico", icons.to_ico_bytes()?)?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+pub mod batch;
+pub mod build;
+mod bytes;
+mod error;
+pub mod favicon;
+pub mod icns;
+mod icon_set;
+pub mod inspect;
+pub mod lint;
+pub mod pe;
+mod raster;
+pub mod rc;
+mod render;
+
+use std::path::Path;
+
+pub use error::Error;
+pub use icon_set::{EntryEncoding, IconEntry, IconSet};
+pub use raster::{raster_dimensions, RasterRenderer, ResizeFilter};
+pub use render::SvgRenderer;
+
+/// Icon sizes generated when no other sizes are requested.
+pub const DEFAULT_SIZES: &[u16] = &[256, 128, 64, 48, 32, 24, 16];
+
+/// File extensions of the raster formats accepted in place of an SVG.
+pub const RASTER_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "webp", "gif", "tif", "tiff", "bmp"];
+
+/// Settings for an SVG conversion.
+#[derive(Debug, Clone)]
+pub struct ConvertOptions {
+    /// Square sizes, in pixels, of the entries to generate (1-256), in output order.
+    pub sizes: Vec<u16>,
+    /// Resolution used to convert physical units (mm, in, pt) in the SVG to pixels.
+    pub dpi: f32,
+    /// Empty margin kept around the artwork on each side, as a fraction of the icon size.
+    pub padding: f32,
+    /// RGBA color painted behind the artwork; `None` keeps the background transparent.
+    pub background: Option<[u8; 4]>,
+    /// Smooths shape edges. Turning it off gives crisp, aliased edges for pixel-aligned artwork.
+    pub anti_alias: bool,
+    /// How each rendered size is stored in the ICO.
+    pub encoding: EntryEncoding,
+    /// Filter used to scale raster sources; SVGs are always rendered directly at each size.
+    pub filter: ResizeFilter,
+}
+
+impl Default for ConvertOptions {
+    fn default() -> Self {
+        ConvertOptions {
+            sizes: DEFAULT_SIZES.to_vec(),
+            dpi: 96.0,
+            padding: 0.0,
+            background: None,
+            anti_alias: true,
+            encoding: EntryEncoding::Png,
+            filter: ResizeFilter::default(),
+        }
+    }
+}
+
+/// Parses a comma-separated size list such as `"256,48,16"`.
+pub fn parse_sizes(text: &str) -> Result<Vec<u16>, Error> {
+    let mut sizes = Vec::new();
+    for part in text.split(',').map(str::trim).filter(|part| !part.is_empty()) {
+        match part.parse::<u16>() {
+            Ok(size) if (1..=256).contains(&size) => sizes.push(size),
+            _ => return Err(Error::ParseSize(part.to_string())),
+        }
+    }
+    if sizes.is_empty() {
+        return Err(Error::NoSizes);
+    }
+    Ok(sizes)
+}
+
+/// Parses an `RRGGBB` or `RRGGBBAA` hex color, with or without a leading `#`, into RGBA.
+pub fn parse_color(text: &str) -> Result<[u8; 4], Error> {
+    let hex = text.trim_start_matches('#');
+    let channel = |index: usize| u8::from_str_radix(&hex[index * 2..index * 2 + 2], 16);
+    let parsed = match hex.len() {
+        6 if hex.bytes().all(|byte| byte.is_ascii_hexdigit()) => (0..3).map(channel).chain([Ok(255)]).collect::<Result<Vec<u8>, _>>(),
+        8 if hex.bytes().all(|byte| byte.is_ascii_hexdigit()) => (0..4).map(channel).collect::<Result<Vec<u8>, _>>(),
+        _ => return Err(Error::ParseColor(text.to_string())),
+    };
+    match parsed {
+        Ok(rgba) => Ok([rgba[0], rgba[1], rgba[2], rgba[3]]),
+        Err(_) => Err(Error::ParseColor(text.to_string())),
+    }
+}
+
+/// Artwork used for one icon size instead of the main SVG, e.g. hand-hinted 16 px pixel art.
+#[derive(Debug, Clone)]
+pub struct SizeOverride {
+    /// The icon size this artwork is used for.
+    pub size: u16,
+    /// SVG bytes, rendered at `size`, or a PNG file that must be exactly `size` x `size`.
+    pub data: Vec<u8>,
+}
+
+impl SizeOverride {
+    /// Reads the override artwork for `size` from an SVG or PNG file.
+    pub fn from_file(size: u16, path: &Path) -> Result<SizeOverride, Error> {
+        Ok(SizeOverride { size, data: std::fs::read(path)? })
+    }
+
+    fn render(&self, options: &ConvertOptions) -> Result<image::RgbaImage, Error> {
+        if !self.data.starts_with(bytes::PNG_SIGNATURE) {
+            return SvgRenderer::new(&self.data, options)?.render(self.size.into());
+        }
+        let image = image::load_from_memory_with_format(&self.data, image::ImageFormat::Png)?.to_rgba8();
+        if image.dimensions() != (self.size.into(), self.size.into()) {
+            return Err(Error::OverrideSize { size: self.size, width: image.width(), height: image.height() });
+        }
+        Ok(image)
+    }
+}
+
+/// Renders an SVG document at every requested size and collects the results into an icon set.
+///
+/// Everything happens in memory: the SVG is parsed once, rasterized per size and encoded straight
+/// into ICO entries.
+pub fn convert_svg(svg: &[u8], options: &ConvertOptions) -> Result<IconSet, Error> {
+    convert_svg_with_overrides(svg, options, &[])
+}
+
+/// Like [`convert_svg`], but sizes that have an entry in `overrides` use that artwork instead of
+/// the main SVG. Overrides for sizes that are not in `options.sizes` are ignored.
+pub fn convert_svg_with_overrides(svg: &[u8], options: &ConvertOptions, overrides: &[SizeOverride]) -> Result<IconSet, Error> {
+    check_sizes(options)?;
+    let renderer = SvgRenderer::new(svg, options)?;
+    build_icon_set(options, overrides, |size| renderer.render(size))
+}
+
+/// Decodes a raster image (PNG, JPEG, WebP, GIF, TIFF or BMP) and scales it to every requested
+/// size with `options.filter`.
+///
+/// Sizes larger than the source are upscaled and come out blurry; see [`upscaled_sizes`].
+pub fn convert_raster(data: &[u8], options: &ConvertOptions) -> Result<IconSet, Error> {
+    convert_raster_with_overrides(data, options, &[])
+}
+
+/// Like [`convert_raster`], with per-size artwork as in [`convert_svg_with_overrides`].
+pub fn convert_raster_with_overrides(data: &[u8], options: &ConvertOptions, overrides: &[SizeOverride]) -> Result<IconSet, Error> {
+    check_sizes(options)?;
+    let renderer = RasterRenderer::new(data, options)?;
+    build_icon_set(options, overrides, |size| renderer.render(size))
+}
+
+/// Returns true if `path` has one of the [`RASTER_EXTENSIONS`].
+pub fn is_raster_path(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| RASTER_EXTENSIONS.iter().any(|raster| ext.eq_ignore_ascii_case(raster)))
+}
+
+/// Returns the requested sizes, largest first, that are bigger than the longer side of a raster
+/// source. Sizes with an override are skipped since they do not use the source.
+pub fn upscaled_sizes(data: &[u8], options: &ConvertOptions, overrides: &[SizeOverride]) -> Result<Vec<u16>, Error> {
+    let (width, height) = raster_dimensions(data)?;
+    let mut sizes: Vec<u16> = options
+        .sizes
+        .iter()
+        .copied()
+        .filter(|&size| u32::from(size) > width.max(height))
+        .filter(|&size| !overrides.iter().any(|size_override| size_override.size == size))
+        .collect();
+    sizes.sort_by_key(|&size| std::cmp::Reverse(size));
+    Ok(sizes)
+}
+
+/// A user-facing warning naming the sizes from [`upscaled_sizes`], or `None` if the raster source is
+/// large enough for all of them.
+pub fn upscale_warning(data: &[u8], options: &ConvertOptions, overrides: &[SizeOverride]) -> Result<Option<String>, Error> {
+    let upscaled = upscaled_sizes(data, options, overrides)?;
+    if upscaled.is_empty() {
+        return Ok(None);
+    }
+    let (width, height) = raster_dimensions(data)?;
+    let sizes = upscaled.iter().map(u16::to_string).collect::<Vec<_>>().join(", ");
+    Ok(Some(format!(
+        "the source image is only {} x {}, so the {} px entries are upscaled and will look blurry",
+        width, height, sizes
+    )))
+}
+
+fn check_sizes(options: &ConvertOptions) -> Result<(), Error> {
+    if options.sizes.is_empty() {
+        return Err(Error::NoSizes);
+    }
+    if let Some(&size) = options.sizes.iter().find(|&&size| size == 0 || size > 256) {
+        return Err(Error::InvalidSize(size.into()));
+    }
+    Ok(())
+}
+
+fn build_icon_set(
+    options: &ConvertOptions,
+    overrides: &[SizeOverride],
+    render: impl Fn(u32) -> Result<image::RgbaImage, Error>,
+) -> Result<IconSet, Error> {
+    let mut icon_set = IconSet::default();
+    for &size in &options.sizes {
+        let image = match overrides.iter().find(|size_override| size_override.size == size) {
+            Some(size_override) => size_override.render(options)?,
+            None => render(size.into())?,
+        };
+        icon_set.push(IconEntry::from_rgba(&image, options.encoding)?)?;
+    }
+    Ok(icon_set)
+}
+
+/// Reads an SVG file from disk and converts it with [`convert_svg`].
+pub fn convert_svg_file(path: &Path, options: &ConvertOptions) -> Result<IconSet, Error> {
+    convert_svg(&std::fs::read(path)?, options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_colors() {
+        assert_eq!(parse_color("#3366cc").unwrap(), [0x33, 0x66, 0xCC, 255]);
+        assert_eq!(parse_color("3366CC80").unwrap(), [0x33, 0x66, 0xCC, 0x80]);
+        // `from_str_radix` alone would take the sign as part of a channel
+        for text in ["+1+2+3", "#12+45678", "12345", "#1234567g", "ééé", ""] {
+            assert!(matches!(parse_color(text), Err(Error::ParseColor(_))), "{}", text);
+        }
+    }
+}
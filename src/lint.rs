@@ -0,0 +1,283 @@
+//! Checks an ICO file for problems that make Windows, Explorer or resource compilers show or
+//! accept it incorrectly.
+
+use std::fmt;
+
+use crate::inspect::{self, EntryInfo};
+use crate::Error;
+
+/// Sizes Windows picks from for Explorer, the taskbar and title bars at common DPI settings.
+pub const STANDARD_SIZES: &[u32] = &[16, 32, 48, 256];
+
+/// Sizes worth adding for sharp icons at 125% and 150% scaling.
+pub const RECOMMENDED_SIZES: &[u32] = &[24, 64];
+
+/// Files larger than this slow down Explorer and bloat executables they are embedded in.
+pub const MAX_FILE_SIZE: usize = 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// A suggestion; the icon works everywhere.
+    Info,
+    /// Works on current Windows but misbehaves in some tools or looks worse than it could.
+    Warning,
+    /// The file is broken or will be shown incorrectly.
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Info => write!(f, "info"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// One problem found by [`lint_ico`].
+#[derive(Debug, Clone)]
+pub struct Finding {
+    pub severity: Severity,
+    /// Index of the entry the finding is about; `None` for the file as a whole.
+    pub entry: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.entry {
+            Some(index) => write!(f, "{}: entry {}: {}", self.severity, index + 1, self.message),
+            None => write!(f, "{}: {}", self.severity, self.message),
+        }
+    }
+}
+
+/// Validates an ICO file and returns the findings, most severe first.
+///
+/// Only a file whose directory cannot be read at all is an `Err`; everything else, including
+/// corrupt entries, is reported as a [`Finding`].
+pub fn lint_ico(data: &[u8]) -> Result<Vec<Finding>, Error> {
+    let entries = inspect::inspect_ico(data)?;
+    let mut findings = Vec::new();
+    let mut add = |severity, entry, message: String| findings.push(Finding { severity, entry, message });
+
+    if entries.is_empty() {
+        add(Severity::Error, None, "the file contains no images".to_string());
+    }
+    if data.len() > MAX_FILE_SIZE {
+        add(
+            Severity::Warning,
+            None,
+            format!("the file is {} KiB; icons over {} KiB slow down Explorer", data.len() / 1024, MAX_FILE_SIZE / 1024),
+        );
+    }
+
+    let directory_end = 6 + 16 * entries.len();
+    for (index, entry) in entries.iter().enumerate() {
+        let Some(image) = &entry.image else {
+            add(
+                Severity::Error,
+                Some(index),
+                format!(
+                    "image data ({} bytes at offset {}) lies outside the file or has no readable header",
+                    entry.byte_size, entry.data_offset
+                ),
+            );
+            continue;
+        };
+        if (entry.data_offset as usize) < directory_end {
+            add(Severity::Error, Some(index), format!("image data at offset {} overlaps the directory", entry.data_offset));
+        }
+        if let Some(other) = entries[..index].iter().position(|other| overlaps(entry, other)) {
+            add(Severity::Error, Some(index), format!("image data overlaps entry {}", other + 1));
+        }
+        if !entry.dimensions_match() {
+            add(
+                Severity::Error,
+                Some(index),
+                format!(
+                    "directory declares {} x {} but the image is {} x {}",
+                    entry.declared_width, entry.declared_height, image.width, image.height
+                ),
+            );
+        }
+        if let Some(declared) = entry.declared_bits_per_pixel.filter(|&bpp| bpp != 0 && bpp != image.bits_per_pixel) {
+            add(
+                Severity::Warning,
+                Some(index),
+                format!("directory declares {} bpp but the image has {} bpp", declared, image.bits_per_pixel),
+            );
+        }
+        if entry.declared_width == 256 && !image.is_png {
+            add(
+                Severity::Warning,
+                Some(index),
+                format!("256 px entry is an uncompressed BMP ({} KiB); store it as PNG", entry.byte_size / 1024),
+            );
+        }
+        // Only a note: Vista and later read these fine, and PNG is what the converter writes by default
+        if entry.declared_width < 256 && image.is_png {
+            add(
+                Severity::Info,
+                Some(index),
+                format!(
+                    "{} x {} entry is PNG; Windows XP and some older resource tools only accept BMP below 256 px",
+                    entry.declared_width, entry.declared_height
+                ),
+            );
+        }
+        let duplicate = entries[..index].iter().position(|other| {
+            other.declared_width == entry.declared_width
+                && other.declared_height == entry.declared_height
+                && other.declared_bits_per_pixel == entry.declared_bits_per_pixel
+        });
+        if let Some(other) = duplicate {
+            add(
+                Severity::Warning,
+                Some(index),
+                format!(
+                    "duplicates entry {} ({} x {}); Windows only ever uses one of them",
+                    other + 1,
+                    entry.declared_width,
+                    entry.declared_height
+                ),
+            );
+        }
+    }
+
+    for &size in STANDARD_SIZES {
+        if !entries.iter().any(|entry| entry.declared_width == size && entry.declared_height == size) {
+            add(Severity::Warning, None, format!("no {} x {} entry; Windows will scale another size instead", size, size));
+        }
+    }
+    for &size in RECOMMENDED_SIZES {
+        if !entries.iter().any(|entry| entry.declared_width == size && entry.declared_height == size) {
+            add(Severity::Info, None, format!("no {} x {} entry for sharp icons at 125% and 150% scaling", size, size));
+        }
+    }
+
+    // Stable sort keeps entry order within each severity
+    findings.sort_by_key(|finding| std::cmp::Reverse(finding.severity));
+    Ok(findings)
+}
+
+fn overlaps(a: &EntryInfo, b: &EntryInfo) -> bool {
+    let (a_start, b_start) = (u64::from(a.data_offset), u64::from(b.data_offset));
+    a_start < b_start + u64::from(b.byte_size) && b_start < a_start + u64::from(a.byte_size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{convert_svg, ConvertOptions, EntryEncoding};
+
+    const SVG: &[u8] = br##"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 16 16"><rect x="2" y="2" width="12" height="12" fill="#3366cc"/></svg>"##;
+
+    fn ico(sizes: &[u16], encoding: EntryEncoding) -> Vec<u8> {
+        let options = ConvertOptions { sizes: sizes.to_vec(), encoding, ..ConvertOptions::default() };
+        convert_svg(SVG, &options).unwrap().to_ico_bytes().unwrap()
+    }
+
+    // Position of a field of directory entry `index`
+    fn field(index: usize, offset: usize) -> usize {
+        6 + 16 * index + offset
+    }
+
+    #[test]
+    fn complete_icon_has_no_findings() {
+        let findings = lint_ico(&ico(&[256, 64, 48, 32, 24, 16], EntryEncoding::Auto)).unwrap();
+        assert!(findings.is_empty(), "{:?}", findings);
+    }
+
+    struct Case {
+        name: &'static str,
+        sizes: &'static [u16],
+        encoding: EntryEncoding,
+        edit: fn(&mut Vec<u8>),
+        severity: Severity,
+        entry: Option<usize>,
+        message: &'static str,
+    }
+
+    const ALL_SIZES: &[u16] = &[256, 48, 32, 16];
+
+    #[test]
+    fn reports_problems() {
+        let cases = [
+            Case {
+                name: "overlap",
+                sizes: ALL_SIZES,
+                encoding: EntryEncoding::Auto,
+                edit: |data| data.copy_within(field(0, 12)..field(0, 16), field(1, 12)),
+                severity: Severity::Error,
+                entry: Some(1),
+                message: "overlaps entry 1",
+            },
+            Case {
+                // One more directory entry makes the directory end after where the first image starts
+                name: "offset inside directory",
+                sizes: ALL_SIZES,
+                encoding: EntryEncoding::Auto,
+                edit: |data| data[4] += 1,
+                severity: Severity::Error,
+                entry: Some(0),
+                message: "overlaps the directory",
+            },
+            Case {
+                name: "duplicate",
+                sizes: ALL_SIZES,
+                encoding: EntryEncoding::Auto,
+                edit: |data| data.copy_within(field(1, 0)..field(1, 2), field(2, 0)),
+                severity: Severity::Warning,
+                entry: Some(2),
+                message: "duplicates entry 2",
+            },
+            Case {
+                name: "256 px BMP",
+                sizes: ALL_SIZES,
+                encoding: EntryEncoding::Bmp,
+                edit: |_| {},
+                severity: Severity::Warning,
+                entry: Some(0),
+                message: "uncompressed BMP",
+            },
+            Case {
+                name: "PNG below 256 px",
+                sizes: ALL_SIZES,
+                encoding: EntryEncoding::Png,
+                edit: |_| {},
+                severity: Severity::Info,
+                entry: Some(3),
+                message: "16 x 16 entry is PNG",
+            },
+            Case {
+                name: "missing standard size",
+                sizes: &[48, 32, 16],
+                encoding: EntryEncoding::Auto,
+                edit: |_| {},
+                severity: Severity::Warning,
+                entry: None,
+                message: "no 256 x 256 entry",
+            },
+        ];
+        for case in cases {
+            let mut data = ico(case.sizes, case.encoding);
+            (case.edit)(&mut data);
+            let findings = lint_ico(&data).unwrap();
+            let found = findings.iter().any(|finding| {
+                finding.severity == case.severity && finding.entry == case.entry && finding.message.contains(case.message)
+            });
+            assert!(found, "{}: {:?}", case.name, findings);
+        }
+    }
+
+    #[test]
+    fn sorts_most_severe_first() {
+        let mut data = ico(&[48, 32], EntryEncoding::Png);
+        data.copy_within(field(0, 12)..field(0, 16), field(1, 12));
+        let severities: Vec<_> = lint_ico(&data).unwrap().iter().map(|finding| finding.severity).collect();
+        assert!(severities.windows(2).all(|pair| pair[0] >= pair[1]), "{:?}", severities);
+        assert_eq!(severities.first(), Some(&Severity::Error));
+    }
+}
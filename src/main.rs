@@ -1,7 +1,11 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use iced::widget::{button, column, container, image, row, scrollable, text, vertical_space};
-use iced::{Alignment, Application, Color, Command, Element, Length, Settings, Size, Theme, alignment, window};
+mod cli;
+mod pixel_view;
+mod settings;
+
+use iced::widget::{button, checkbox, column, container, image, mouse_area, pick_list, row, scrollable, slider, text, text_input};
+use iced::{Alignment, Application, Color, Command, Element, Event, Length, Settings, Size, Subscription, Theme, alignment, event, mouse, window};
 
 struct MyContainerStyle(Color);
 
@@ -21,6 +25,25 @@ impl container::StyleSheet for MyContainerStyle {
     }
 }
 
+// Highlights the preview container while files are dragged over the window
+struct DropTargetStyle(Color);
+
+impl container::StyleSheet for DropTargetStyle {
+    type Style = Theme;
+
+    fn appearance(&self, _style: &Self::Style) -> container::Appearance {
+        container::Appearance {
+            background: Some(iced::Background::Color(self.0)),
+            border: iced::Border {
+                color: Color::from_rgb(64.0 / 255.0, 160.0 / 255.0, 1.0), // #40A0FF
+                width: 3.0,
+                radius: 10.0.into(),
+            },
+            ..Default::default()
+        }
+    }
+}
+
 struct MainBgStyle(Color);
 
 impl container::StyleSheet for MainBgStyle {
@@ -34,6 +57,63 @@ impl container::StyleSheet for MainBgStyle {
     }
 }
 
+// Primary checkbox with a fixed label color, for labels on the custom backgrounds
+struct SizeCheckboxStyle(Color);
+
+impl checkbox::StyleSheet for SizeCheckboxStyle {
+    type Style = Theme;
+
+    fn active(&self, style: &Self::Style, is_checked: bool) -> checkbox::Appearance {
+        checkbox::Appearance {
+            text_color: Some(self.0),
+            ..style.active(&iced::theme::Checkbox::Primary, is_checked)
+        }
+    }
+
+    fn hovered(&self, style: &Self::Style, is_checked: bool) -> checkbox::Appearance {
+        checkbox::Appearance {
+            text_color: Some(self.0),
+            ..style.hovered(&iced::theme::Checkbox::Primary, is_checked)
+        }
+    }
+}
+
+struct ErrorBannerStyle;
+
+impl container::StyleSheet for ErrorBannerStyle {
+    type Style = Theme;
+
+    fn appearance(&self, _style: &Self::Style) -> container::Appearance {
+        container::Appearance {
+            background: Some(iced::Background::Color(Color::from_rgb(160.0 / 255.0, 40.0 / 255.0, 40.0 / 255.0))), // #A02828
+            text_color: Some(Color::WHITE),
+            border: iced::Border {
+                radius: 6.0.into(),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+}
+
+struct NoticeBannerStyle;
+
+impl container::StyleSheet for NoticeBannerStyle {
+    type Style = Theme;
+
+    fn appearance(&self, _style: &Self::Style) -> container::Appearance {
+        container::Appearance {
+            background: Some(iced::Background::Color(Color::from_rgb(40.0 / 255.0, 120.0 / 255.0, 60.0 / 255.0))), // #28783C
+            text_color: Some(Color::WHITE),
+            border: iced::Border {
+                radius: 6.0.into(),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+}
+
 struct LogoStyle;
 
 impl container::StyleSheet for LogoStyle {
@@ -50,35 +130,487 @@ impl container::StyleSheet for LogoStyle {
         }
     }
 }
-use std::io;
+use std::path::{Path, PathBuf};
+
+use pixel_view::PixelView;
+use settings::{RecentFile, Settings as AppSettings};
+use rusty_svg2ico::favicon;
+use rusty_svg2ico::icns::{self, IcnsEntry};
+use rusty_svg2ico::inspect::{self, EntryInfo};
+use rusty_svg2ico::lint::{self, Finding, Severity};
+use rusty_svg2ico::pe::{self, IconGroup};
+use rusty_svg2ico::rc::{self, VersionInfo};
+use rusty_svg2ico::{ConvertOptions, IconEntry, IconSet, ResizeFilter, SizeOverride, SvgRenderer, DEFAULT_SIZES, RASTER_EXTENSIONS};
 
 // Embed the logo image data at compile time so it's included in the executable
 static LOGO_DATA: &[u8] = include_bytes!("../assets/RUSTYSVG2ICO420.png");
 
+// Sizes offered as checkboxes; DEFAULT_SIZES start checked
+const STANDARD_SIZES: &[u16] = &[256, 128, 96, 72, 64, 48, 40, 32, 24, 20, 16];
+
+// Filters offered for scaling raster sources
+const RESIZE_FILTERS: [ResizeFilter; 5] = [
+    ResizeFilter::Lanczos3,
+    ResizeFilter::CatmullRom,
+    ResizeFilter::Gaussian,
+    ResizeFilter::Triangle,
+    ResizeFilter::Nearest,
+];
+
+// What the preview images are shown against
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PreviewBackground {
+    Checkerboard,
+    White,
+    Black,
+    TaskbarLight,
+    TaskbarDark,
+    Custom,
+}
+
+impl PreviewBackground {
+    const ALL: [PreviewBackground; 6] = [
+        PreviewBackground::Checkerboard,
+        PreviewBackground::White,
+        PreviewBackground::Black,
+        PreviewBackground::TaskbarLight,
+        PreviewBackground::TaskbarDark,
+        PreviewBackground::Custom,
+    ];
+}
+
+impl std::fmt::Display for PreviewBackground {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            PreviewBackground::Checkerboard => "Checkerboard",
+            PreviewBackground::White => "White",
+            PreviewBackground::Black => "Black",
+            PreviewBackground::TaskbarLight => "Windows taskbar (light)",
+            PreviewBackground::TaskbarDark => "Windows taskbar (dark)",
+            PreviewBackground::Custom => "Custom color",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// Light/dark choice; System follows the OS setting while the app runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+enum ThemeMode {
+    #[default]
+    System,
+    Light,
+    Dark,
+}
+
+impl ThemeMode {
+    const ALL: [ThemeMode; 3] = [ThemeMode::System, ThemeMode::Light, ThemeMode::Dark];
+}
+
+impl std::fmt::Display for ThemeMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            ThemeMode::System => "System",
+            ThemeMode::Light => "Light",
+            ThemeMode::Dark => "Dark",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// How often the OS theme is checked while following it
+const THEME_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(2);
+
+// Black or white, whichever reads better on `background`
+fn text_color_on(background: Color) -> Color {
+    let luminance = 0.299 * background.r + 0.587 * background.g + 0.114 * background.b;
+    if luminance > 0.5 { Color::BLACK } else { Color::WHITE }
+}
+
+// One entry of the loaded icon as shown in the preview list
+struct PreviewImage {
+    handle: Option<iced::widget::image::Handle>,
+    // Pixels behind `handle`, for the color readout in the zoomed view
+    rgba: Option<::image::RgbaImage>,
+    label: String,
+    width: u32,
+    height: u32,
+    // File the entry was rendered from, for icons generated with size overrides
+    source: Option<String>,
+}
+
+// Icon groups found in an EXE or DLL, listed until one is opened
+struct PeGroupList {
+    path: PathBuf,
+    // Each group with its size summary and a small thumbnail
+    groups: Vec<(IconGroup, String, Option<iced::widget::image::Handle>)>,
+}
+
+impl PeGroupList {
+    fn new(path: PathBuf, groups: Vec<IconGroup>) -> PeGroupList {
+        let groups = groups
+            .into_iter()
+            .map(|group| {
+                let icon_set = IconSet::from_ico_bytes(&group.ico).unwrap_or_default();
+                let mut sizes: Vec<u32> = icon_set.entries().iter().map(IconEntry::width).collect();
+                sizes.sort_unstable_by(|a, b| b.cmp(a));
+                sizes.dedup();
+                let sizes = sizes.iter().map(u32::to_string).collect::<Vec<_>>().join(", ");
+                let summary = format!("{} (language {})\n{} images: {} px", group.name, group.language, icon_set.len(), sizes);
+                // The largest image that still fits the list, or the smallest one
+                let thumbnail = icon_set
+                    .entries()
+                    .iter()
+                    .filter(|entry| entry.width() <= 48)
+                    .max_by_key(|entry| (entry.width(), entry.bits_per_pixel()))
+                    .or_else(|| icon_set.entries().iter().min_by_key(|entry| entry.width()))
+                    .and_then(|entry| entry.to_rgba().ok())
+                    .map(image_handle);
+                (group, summary, thumbnail)
+            })
+            .collect();
+        PeGroupList { path, groups }
+    }
+}
+
+// Where an image picked while editing goes
+#[derive(Debug, Clone, Copy)]
+enum EntryEdit {
+    Add,
+    Replace(usize),
+}
+
 struct SvgToIcoApp {
     ico_data: Option<Vec<u8>>,
-    images: Vec<(iced::widget::image::Handle, String)>,
-    is_generated: bool,
+    images: Vec<PreviewImage>,
+    // Cursor hotspot per entry of `images`, in that entry's pixels
+    hotspots: Vec<(u16, u16)>,
+    hotspot_mode: bool,
+    per_size_hotspot: bool,
+    // Header details per entry of `images`, empty for ICNS files
+    entry_info: Vec<EntryInfo>,
+    show_details: bool,
+    // Entry of `images` shown magnified instead of the list
+    zoom: Option<usize>,
+    zoom_scale: f32,
+    zoom_grid: bool,
+    hovered_pixel: Option<(u16, u16)>,
+    preview_background: PreviewBackground,
+    // Hex text of the custom background and the last color it parsed to
+    custom_background: String,
+    custom_color: Color,
+    edit_mode: bool,
+    // Size an SVG is rendered at when added as a new entry
+    edit_size: String,
+    // The file the current icon was opened or generated from
+    source_path: Option<PathBuf>,
+    // The SVG the current icon was generated from, kept for re-rendering into other formats
+    svg_source: Option<Vec<u8>>,
     logo: Option<iced::widget::image::Handle>,
-    is_dark: bool,
+    theme_mode: ThemeMode,
+    // Last theme reported by the OS
+    system_dark: bool,
+    sizes: Vec<(u16, bool)>,
+    custom_size: String,
+    // Artwork used for single sizes instead of the main SVG
+    overrides: Vec<(u16, PathBuf)>,
+    override_size: String,
+    // Filter for scaling PNG, JPEG and other raster sources
+    filter: ResizeFilter,
+    error: Option<String>,
+    notice: Option<String>,
+    batch_summary: Option<Vec<(PathBuf, Result<(), String>)>>,
+    // Windows compatibility findings for the loaded icon, shown instead of the preview
+    lint_findings: Option<Vec<Finding>>,
+    pe_groups: Option<PeGroupList>,
+    // Whether "Save Icon" also writes a resource script with this version information
+    write_rc: bool,
+    version_info: VersionInfo,
+    is_file_hovered: bool,
+    // Preferences, folders and recent files saved between runs
+    settings: AppSettings,
 }
 
 #[derive(Debug, Clone)]
 enum Message {
     SelectSvg,
+    ConvertImage(PathBuf),
     OpenIco,
+    LoadIco(PathBuf),
+    OpenPe,
+    LoadPe(PathBuf),
+    PeGroupsLoaded(PathBuf, Vec<IconGroup>),
+    OpenPeGroup(usize),
+    ClosePeGroups,
     SaveIcon,
-    IcoLoaded(Vec<u8>, bool),
+    SaveIcns,
+    SaveCursor,
+    ResourceScriptWritten(PathBuf),
+    ToggleWriteRc(bool),
+    ProductNameChanged(String),
+    VersionChanged(String),
+    CompanyChanged(String),
+    PickCargoToml,
+    VersionInfoLoaded(VersionInfo),
+    WriteIntoExe,
+    ExeWritten(PathBuf, usize),
+    ExportFavicons,
+    ExportPng(usize),
+    ExportAllPngs,
+    CheckCompatibility,
+    CloseCompatibility,
+    FaviconsExported(PathBuf, String),
+    OutputDirUsed(PathBuf),
+    OpenRecent(RecentFile),
+    ToggleHotspotMode(bool),
+    TogglePerSizeHotspot(bool),
+    ToggleDetails(bool),
+    OpenZoom(usize),
+    CloseZoom,
+    ZoomScaleChanged(f32),
+    ToggleGrid(bool),
+    PixelHovered(Option<(u16, u16)>),
+    ThemeModeChanged(ThemeMode),
+    CheckSystemTheme,
+    SystemThemeDetected(bool),
+    BackgroundChanged(PreviewBackground),
+    CustomBackgroundChanged(String),
+    ToggleEditMode(bool),
+    EditSizeChanged(String),
+    PickEntryImage(EntryEdit),
+    EntryImageLoaded(EntryEdit, ::image::RgbaImage),
+    MoveEntry(usize, usize),
+    RemoveEntry(usize),
+    SetHotspot(usize, u16, u16),
+    BatchConvert,
+    BatchFinished(PathBuf, PathBuf, Vec<(PathBuf, Result<(), String>)>),
+    IcoLoaded(PathBuf, Vec<u8>, Option<Vec<u8>>),
+    RasterConverted(PathBuf, Vec<u8>, Option<String>),
+    IcnsLoaded(PathBuf, Vec<IcnsEntry>),
+    ToggleSize(u16, bool),
+    CustomSizeChanged(String),
+    AddCustomSize,
+    OverrideSizeChanged(String),
+    FilterChanged(ResizeFilter),
+    PickOverride,
+    OverridePicked(u16, PathBuf),
+    RemoveOverride(u16),
+    FileHovered,
+    FilesHoveredLeft,
+    FileDropped(PathBuf),
+    WindowResized(u32, u32),
+    CloseRequested,
+    Error(String),
+    DismissError,
+    DismissNotice,
+    Idle,
+}
+
+// Open/save dialog starting in `dir` when there is a remembered folder
+fn file_dialog(dir: Option<PathBuf>) -> rfd::FileDialog {
+    match dir {
+        Some(dir) => rfd::FileDialog::new().set_directory(dir),
+        None => rfd::FileDialog::new(),
+    }
+}
+
+fn folder_of(path: &Path) -> PathBuf {
+    path.parent().unwrap_or(path).to_path_buf()
+}
+
+fn image_handle(rgba: ::image::RgbaImage) -> iced::widget::image::Handle {
+    iced::widget::image::Handle::from_pixels(rgba.width(), rgba.height(), rgba.into_raw())
+}
+
+// Multi-line summary of an entry's header for the details view
+fn entry_details(info: &EntryInfo) -> String {
+    let declared_bpp = info.declared_bits_per_pixel.map_or_else(|| "hotspot".to_string(), |bpp| format!("{} bpp", bpp));
+    let declared = format!(
+        "declared {} x {}, {}, {} colors",
+        info.declared_width, info.declared_height, declared_bpp, info.declared_colors
+    );
+    let location = format!("{} bytes at offset {}", info.byte_size, info.data_offset);
+    let Some(image) = &info.image else {
+        return format!("{}\n{}\nimage data out of bounds or unreadable", declared, location);
+    };
+    let palette = match image.palette_colors {
+        0 => "no palette".to_string(),
+        colors => format!("palette of {}", colors),
+    };
+    let encoding = if image.is_png { "PNG" } else { "BMP" };
+    let actual = format!("{}, {} bpp, {}", encoding, image.bits_per_pixel, palette);
+    if info.dimensions_match() {
+        format!("{}\n{}\n{}", actual, location, declared)
+    } else {
+        format!("{}\n{}\n{}\nactual size {} x {} does not match", actual, location, declared, image.width, image.height)
+    }
 }
 
 impl SvgToIcoApp {
-    fn load_images(&mut self, data: &[u8]) {
-        let icon_dir = ico::IconDir::read(io::Cursor::new(data)).unwrap();
-        self.images.clear();
-        for entry in icon_dir.entries() {
-            let handle = iced::widget::image::Handle::from_memory(entry.data().to_vec());
-            self.images.push((handle, format!("{} x {}", entry.width(), entry.height())));
+    fn selected_sizes(&self) -> Vec<u16> {
+        self.sizes.iter().filter(|(_, checked)| *checked).map(|(size, _)| *size).collect()
+    }
+
+    fn convert_options(&self) -> ConvertOptions {
+        ConvertOptions {
+            sizes: self.selected_sizes(),
+            filter: self.filter,
+            ..Default::default()
+        }
+    }
+
+    fn is_dark(&self) -> bool {
+        match self.theme_mode {
+            ThemeMode::System => self.system_dark,
+            ThemeMode::Light => false,
+            ThemeMode::Dark => true,
+        }
+    }
+
+    // Base name for files derived from the current icon
+    fn source_stem(&self) -> String {
+        self.source_path
+            .as_ref()
+            .and_then(|path| path.file_stem())
+            .map_or_else(|| "icon".to_string(), |stem| stem.to_string_lossy().into_owned())
+    }
+
+    // Copies the current preferences into the settings and writes the settings file
+    fn save_settings(&mut self) {
+        self.settings.sizes = Some(self.selected_sizes());
+        self.settings.custom_sizes = self.sizes.iter().map(|(size, _)| *size).filter(|size| !STANDARD_SIZES.contains(size)).collect();
+        self.settings.theme = self.theme_mode;
+        if let Err(err) = self.settings.save() {
+            self.error = Some(format!("Could not save settings: {}", err));
+        }
+    }
+
+    // Converts SVGs and opens icons, choosing by file extension
+    fn open_file(&mut self, path: PathBuf) -> Command<Message> {
+        let extension = path.extension().and_then(|ext| ext.to_str()).map(str::to_ascii_lowercase);
+        match extension.as_deref() {
+            Some("ico" | "cur" | "icns") => self.update(Message::LoadIco(path)),
+            Some(ext) if pe::PE_EXTENSIONS.contains(&ext) => self.update(Message::LoadPe(path)),
+            Some(ext) if ext == "svg" || RASTER_EXTENSIONS.contains(&ext) => {
+                if self.selected_sizes().is_empty() {
+                    self.update(Message::Error("Select at least one icon size before converting".to_string()))
+                } else {
+                    self.update(Message::ConvertImage(path))
+                }
+            }
+            _ => self.update(Message::Error(format!("{}: only SVG, image, ICO, CUR, ICNS, EXE and DLL files can be opened", path.display()))),
+        }
+    }
+
+    // Labels each preview entry of a freshly generated icon with the file it was rendered from
+    fn mark_sources(&mut self, main_svg: &Path) {
+        let file_name = |path: &Path| path.file_name().map(|name| name.to_string_lossy().into_owned());
+        for preview in &mut self.images {
+            let size_override = self.overrides.iter().find(|(size, _)| u32::from(*size) == preview.width);
+            preview.source = match size_override {
+                Some((_, path)) => file_name(path),
+                None => file_name(main_svg),
+            };
+        }
+    }
+
+    // One entry magnified with nearest-neighbour scaling, an optional pixel grid and a color readout
+    fn zoom_view<'a>(&'a self, index: usize, preview: &'a PreviewImage, text_color: Color) -> Element<'a, Message> {
+        let label = iced::theme::Text::Color(text_color);
+        let header = row![
+            button(text("Back").size(12)).padding([2, 6]).on_press(Message::CloseZoom),
+            text(format!("{} x {} at {}x", preview.width, preview.height, self.zoom_scale)).size(12).style(label),
+            slider(2.0..=32.0, self.zoom_scale, Message::ZoomScaleChanged).step(1.0).width(Length::Fill),
+            checkbox("Grid", self.zoom_grid)
+                .on_toggle(Message::ToggleGrid)
+                .size(14)
+                .style(iced::theme::Checkbox::Custom(Box::new(SizeCheckboxStyle(text_color)))),
+        ]
+        .spacing(8)
+        .align_items(Alignment::Center);
+
+        let color = self.hovered_pixel.and_then(|(x, y)| {
+            let pixel = preview.rgba.as_ref()?.get_pixel_checked(x.into(), y.into())?;
+            Some((x, y, pixel.0))
+        });
+        let readout = match color {
+            Some((x, y, [r, g, b, a])) => format!(
+                "{}, {}:  R {}  G {}  B {}  A {}  #{:02X}{:02X}{:02X}{:02X}",
+                x, y, r, g, b, a, r, g, b, a
+            ),
+            None => "Hover a pixel to read its color".to_string(),
+        };
+
+        let pixels = preview.handle.as_ref().map(|handle| {
+            let view = PixelView::new(handle.clone(), preview.width, preview.height)
+                .scale(self.zoom_scale)
+                .grid(self.zoom_grid)
+                .checkerboard(self.preview_background == PreviewBackground::Checkerboard)
+                .on_hover(Message::PixelHovered);
+            // Placing the hotspot works here too, with pixel precision
+            if self.hotspot_mode {
+                view.marker(self.hotspots.get(index).copied())
+                    .on_press(move |x, y| Message::SetHotspot(index, x, y))
+            } else {
+                view
+            }
+        });
+        let pixels = scrollable(row![].push_maybe(pixels))
+            .direction(scrollable::Direction::Both {
+                vertical: scrollable::Properties::default(),
+                horizontal: scrollable::Properties::default(),
+            })
+            .width(Length::Fill)
+            .height(Length::Fill);
+
+        column![header, text(readout).size(12).style(label), pixels]
+            .spacing(6)
+            .width(Length::Fixed(380.0))
+            .height(Length::Fill)
+            .into()
+    }
+
+    // Applies an edit to the loaded icon and refreshes the preview from the re-encoded file
+    fn edit_icon(&mut self, edit: impl FnOnce(&mut IconSet) -> Result<(), rusty_svg2ico::Error>) {
+        let Some(data) = &self.ico_data else {
+            return;
+        };
+        let result = IconSet::from_ico_bytes(data).and_then(|mut icon_set| {
+            edit(&mut icon_set)?;
+            let data = icon_set.to_ico_bytes()?;
+            self.load_images(&data)?;
+            Ok(data)
+        });
+        match result {
+            Ok(data) => {
+                // Keep an open compatibility report in step with the edits
+                if self.lint_findings.is_some() {
+                    self.lint_findings = lint::lint_ico(&data).ok();
+                }
+                self.ico_data = Some(data);
+                self.error = None;
+            }
+            Err(err) => self.error = Some(err.to_string()),
+        }
+    }
+
+    // Only replaces the current images once the whole file has parsed
+    fn load_images(&mut self, data: &[u8]) -> Result<(), rusty_svg2ico::Error> {
+        let icon_set = IconSet::from_ico_bytes(data)?;
+        let mut images = Vec::with_capacity(icon_set.len());
+        for entry in icon_set.entries() {
+            // Decode to RGBA here: iced only understands PNG, not the BMP/DIB data older icons use
+            let label = format!("{} x {}", entry.width(), entry.height());
+            let (handle, rgba, label) = match entry.to_rgba() {
+                Ok(rgba) => (Some(image_handle(rgba.clone())), Some(rgba), label),
+                Err(err) => (None, None, format!("{}\n{}", label, err)),
+            };
+            images.push(PreviewImage { handle, rgba, label, width: entry.width(), height: entry.height(), source: None });
         }
+        self.hotspots = icon_set.entries().iter().map(|entry| entry.cursor_hotspot().unwrap_or((0, 0))).collect();
+        self.entry_info = inspect::inspect_ico(data).unwrap_or_default();
+        self.images = images;
+        self.zoom = None;
+        Ok(())
     }
 }
 
@@ -86,15 +618,52 @@ impl Application for SvgToIcoApp {
     type Message = Message;
     type Theme = Theme;
     type Executor = iced::executor::Default;
-    type Flags = bool;
+    type Flags = (bool, AppSettings);
 
-    fn new(flags: bool) -> (Self, Command<Message>) {
+    fn new((system_dark, settings): (bool, AppSettings)) -> (Self, Command<Message>) {
+        let checked = settings.sizes.clone().unwrap_or_else(|| DEFAULT_SIZES.to_vec());
+        let mut sizes: Vec<(u16, bool)> = STANDARD_SIZES
+            .iter()
+            .chain(settings.custom_sizes.iter().filter(|size| !STANDARD_SIZES.contains(size)))
+            .map(|size| (*size, checked.contains(size)))
+            .collect();
+        sizes.sort_by_key(|(size, _)| std::cmp::Reverse(*size));
         let mut app = SvgToIcoApp {
             ico_data: None,
             images: vec![],
-            is_generated: false,
+            hotspots: vec![],
+            hotspot_mode: false,
+            per_size_hotspot: false,
+            entry_info: vec![],
+            show_details: false,
+            zoom: None,
+            zoom_scale: 8.0,
+            zoom_grid: true,
+            hovered_pixel: None,
+            preview_background: PreviewBackground::Checkerboard,
+            custom_background: "FF00FF".to_string(),
+            custom_color: Color::from_rgb8(0xFF, 0x00, 0xFF),
+            edit_mode: false,
+            edit_size: "48".to_string(),
+            source_path: None,
+            svg_source: None,
             logo: None,
-            is_dark: flags,
+            theme_mode: settings.theme,
+            system_dark,
+            sizes,
+            custom_size: String::new(),
+            overrides: vec![],
+            override_size: "16".to_string(),
+            filter: ResizeFilter::default(),
+            error: None,
+            notice: None,
+            batch_summary: None,
+            lint_findings: None,
+            pe_groups: None,
+            write_rc: false,
+            version_info: VersionInfo::default(),
+            is_file_hovered: false,
+            settings,
         };
         app.logo = Some(iced::widget::image::Handle::from_memory(LOGO_DATA.to_vec()));
         (app, Command::none())
@@ -107,74 +676,843 @@ impl Application for SvgToIcoApp {
     fn update(&mut self, message: Message) -> Command<Message> {
         match message {
             Message::SelectSvg => {
+                let dir = self.settings.input_dir.clone();
                 Command::perform(
                     async {
-                        tokio::task::spawn_blocking(|| {
-                            rfd::FileDialog::new().add_filter("SVG", &["svg"]).pick_file()
-                        }).await.unwrap()
+                        tokio::task::spawn_blocking(move || {
+                            let extensions: Vec<&str> = ["svg"].iter().chain(RASTER_EXTENSIONS).copied().collect();
+                            file_dialog(dir).add_filter("SVG and images", &extensions).pick_file()
+                        }).await.ok().flatten()
                     },
-                    |path_opt| {
-                        if let Some(path) = path_opt {
-                            let temp_dir = tempfile::TempDir::new().unwrap();
-                            let temp_path = temp_dir.path().join("temp.ico");
-                            svg_to_ico::svg_to_ico(&path, 256.0, &temp_path, &[256u16, 128, 64, 48, 32, 24, 16]).unwrap();
-                            let ico_data = std::fs::read(&temp_path).unwrap();
-                            Message::IcoLoaded(ico_data, true)
-                        } else {
-                            Message::IcoLoaded(vec![], false)
-                        }
-                    }
+                    |path_opt| path_opt.map_or(Message::Idle, Message::ConvertImage)
+                )
+            }
+            Message::ConvertImage(path) => {
+                let options = self.convert_options();
+                let override_paths = self.overrides.clone();
+                Command::perform(
+                    async {
+                        tokio::task::spawn_blocking(move || {
+                            let overrides = override_paths
+                                .iter()
+                                .map(|(size, path)| {
+                                    SizeOverride::from_file(*size, path).map_err(|err| format!("{}: {}", path.display(), err))
+                                })
+                                .collect::<Result<Vec<_>, _>>()?;
+                            std::fs::read(&path)
+                                .map_err(rusty_svg2ico::Error::from)
+                                .and_then(|source| {
+                                    if rusty_svg2ico::is_raster_path(&path) {
+                                        let warning = rusty_svg2ico::upscale_warning(&source, &options, &overrides)?;
+                                        let icon_set = rusty_svg2ico::convert_raster_with_overrides(&source, &options, &overrides)?;
+                                        Ok(Message::RasterConverted(path.clone(), icon_set.to_ico_bytes()?, warning))
+                                    } else {
+                                        let icon_set = rusty_svg2ico::convert_svg_with_overrides(&source, &options, &overrides)?;
+                                        Ok(Message::IcoLoaded(path.clone(), icon_set.to_ico_bytes()?, Some(source)))
+                                    }
+                                })
+                                .map_err(|err| format!("{}: {}", path.display(), err))
+                        }).await.unwrap_or_else(|err| Err(err.to_string()))
+                    },
+                    |result| result.unwrap_or_else(Message::Error)
                 )
             }
             Message::OpenIco => {
+                let dir = self.settings.input_dir.clone();
                 Command::perform(
                     async {
-                        tokio::task::spawn_blocking(|| {
-                            rfd::FileDialog::new().add_filter("ICO", &["ico"]).pick_file()
-                        }).await.unwrap()
+                        tokio::task::spawn_blocking(move || {
+                            file_dialog(dir).add_filter("Icons", &["ico", "cur", "icns"]).pick_file()
+                        }).await.ok().flatten()
                     },
-                    |path_opt| {
-                        if let Some(path) = path_opt {
-                            let ico_data = std::fs::read(path).unwrap();
-                            Message::IcoLoaded(ico_data, false)
-                        } else {
-                            Message::IcoLoaded(vec![], false)
-                        }
+                    |path_opt| path_opt.map_or(Message::Idle, Message::LoadIco)
+                )
+            }
+            Message::LoadIco(path) if path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("icns")) => {
+                Command::perform(
+                    async {
+                        tokio::task::spawn_blocking(move || {
+                            std::fs::read(&path)
+                                .map_err(rusty_svg2ico::Error::from)
+                                .and_then(|data| icns::read_icns(&data))
+                                .map(|entries| (path.clone(), entries))
+                                .map_err(|err| format!("{}: {}", path.display(), err))
+                        }).await.unwrap_or_else(|err| Err(err.to_string()))
+                    },
+                    |result| match result {
+                        Ok((path, entries)) => Message::IcnsLoaded(path, entries),
+                        Err(err) => Message::Error(err),
                     }
                 )
             }
+            Message::LoadIco(path) => {
+                Command::perform(
+                    async {
+                        tokio::task::spawn_blocking(move || {
+                            std::fs::read(&path)
+                                .map_err(rusty_svg2ico::Error::from)
+                                .and_then(|data| IconSet::from_ico_bytes(&data).map(|_| (path.clone(), data)))
+                                .map_err(|err| format!("{}: {}", path.display(), err))
+                        }).await.unwrap_or_else(|err| Err(err.to_string()))
+                    },
+                    |result| match result {
+                        Ok((path, ico_data)) => Message::IcoLoaded(path, ico_data, None),
+                        Err(err) => Message::Error(err),
+                    }
+                )
+            }
+            Message::OpenPe => {
+                let dir = self.settings.input_dir.clone();
+                Command::perform(
+                    async {
+                        tokio::task::spawn_blocking(move || {
+                            file_dialog(dir).add_filter("Executables and DLLs", pe::PE_EXTENSIONS).pick_file()
+                        }).await.ok().flatten()
+                    },
+                    |path_opt| path_opt.map_or(Message::Idle, Message::LoadPe)
+                )
+            }
+            Message::LoadPe(path) => {
+                Command::perform(
+                    async {
+                        tokio::task::spawn_blocking(move || {
+                            std::fs::read(&path)
+                                .map_err(rusty_svg2ico::Error::from)
+                                .and_then(|data| pe::read_icon_groups(&data))
+                                .map(|groups| (path.clone(), groups))
+                                .map_err(|err| format!("{}: {}", path.display(), err))
+                        }).await.unwrap_or_else(|err| Err(err.to_string()))
+                    },
+                    |result| match result {
+                        Ok((path, groups)) => Message::PeGroupsLoaded(path, groups),
+                        Err(err) => Message::Error(err),
+                    }
+                )
+            }
+            Message::PeGroupsLoaded(path, mut groups) => match groups.len() {
+                0 => self.update(Message::Error(format!("{}: the file contains no icons", path.display()))),
+                // Nothing to choose from, so go straight to the viewer
+                1 => self.update(Message::IcoLoaded(path, groups.remove(0).ico, None)),
+                _ => {
+                    self.pe_groups = Some(PeGroupList::new(path, groups));
+                    self.batch_summary = None;
+                    self.lint_findings = None;
+                    self.zoom = None;
+                    self.error = None;
+                    Command::none()
+                }
+            },
+            Message::OpenPeGroup(index) => {
+                match self.pe_groups.take() {
+                    Some(mut list) if index < list.groups.len() => {
+                        let (group, _, _) = list.groups.swap_remove(index);
+                        self.update(Message::IcoLoaded(list.path, group.ico, None))
+                    }
+                    _ => Command::none(),
+                }
+            }
+            Message::ClosePeGroups => {
+                self.pe_groups = None;
+                Command::none()
+            }
             Message::SaveIcon => {
                 if let Some(data) = &self.ico_data {
                     let data = data.clone();
+                    let version_info = self.write_rc.then(|| self.version_info.clone());
+                    let output_dir = self.settings.output_dir.clone();
                     Command::perform(
                         async {
-                            tokio::task::spawn_blocking(|| {
-                                rfd::FileDialog::new().add_filter("ICO", &["ico"]).save_file()
-                            }).await.unwrap()
+                            tokio::task::spawn_blocking(move || {
+                                let path = file_dialog(output_dir).add_filter("ICO", &["ico"]).save_file()?;
+                                let result = std::fs::write(&path, &data)
+                                    .map_err(|err| format!("{}: {}", path.display(), err))
+                                    .and_then(|()| {
+                                        let Some(version_info) = version_info else {
+                                            return Ok(None);
+                                        };
+                                        // The script names the icon without a folder, so both files have to stay together
+                                        let rc_path = path.with_extension("rc");
+                                        let icon_file = path.file_name().unwrap_or_default().to_string_lossy();
+                                        std::fs::write(&rc_path, rc::resource_script(&icon_file, Some(&version_info)))
+                                            .map(|()| Some(rc_path.clone()))
+                                            .map_err(|err| format!("{}: {}", rc_path.display(), err))
+                                    });
+                                Some(result.map(|rc_path| (folder_of(&path), rc_path)))
+                            }).await.unwrap_or_else(|err| Some(Err(err.to_string())))
                         },
-                        move |path_opt| {
-                            if let Some(path) = path_opt {
-                                std::fs::write(path, &data).unwrap();
-                            }
-                            Message::IcoLoaded(vec![], false) // dummy
+                        |result| match result {
+                            Some(Ok((_, Some(rc_path)))) => Message::ResourceScriptWritten(rc_path),
+                            Some(Ok((dir, None))) => Message::OutputDirUsed(dir),
+                            Some(Err(err)) => Message::Error(err),
+                            None => Message::Idle,
                         }
                     )
                 } else {
                     Command::none()
                 }
             }
-            Message::IcoLoaded(data, generated) => {
-                if !data.is_empty() {
-                    self.ico_data = Some(data.clone());
-                    self.is_generated = generated;
-                    self.load_images(&data);
+            Message::SaveIcns => {
+                if let Some(svg) = &self.svg_source {
+                    let svg = svg.clone();
+                    let options = self.convert_options();
+                    let output_dir = self.settings.output_dir.clone();
+                    Command::perform(
+                        async {
+                            tokio::task::spawn_blocking(move || {
+                                let path = file_dialog(output_dir).add_filter("ICNS", &["icns"]).save_file()?;
+                                let result = icns::svg_to_icns(&svg, &options)
+                                    .and_then(|data| std::fs::write(&path, data).map_err(rusty_svg2ico::Error::from))
+                                    .map(|()| folder_of(&path))
+                                    .map_err(|err| format!("{}: {}", path.display(), err));
+                                Some(result)
+                            }).await.unwrap_or_else(|err| Some(Err(err.to_string())))
+                        },
+                        |result| match result {
+                            Some(Ok(dir)) => Message::OutputDirUsed(dir),
+                            Some(Err(err)) => Message::Error(err),
+                            None => Message::Idle,
+                        }
+                    )
+                } else {
+                    Command::none()
+                }
+            }
+            Message::SaveCursor => {
+                let cursor = self
+                    .ico_data
+                    .as_ref()
+                    .map(|data| IconSet::from_ico_bytes(data).and_then(|icon_set| icon_set.to_cur_bytes(&self.hotspots)));
+                let output_dir = self.settings.output_dir.clone();
+                match cursor {
+                    Some(Ok(data)) => Command::perform(
+                        async {
+                            tokio::task::spawn_blocking(move || {
+                                let path = file_dialog(output_dir).add_filter("Cursor", &["cur"]).save_file()?;
+                                let result = std::fs::write(&path, &data).map_err(|err| format!("{}: {}", path.display(), err));
+                                Some(result.map(|()| folder_of(&path)))
+                            }).await.unwrap_or_else(|err| Some(Err(err.to_string())))
+                        },
+                        |result| match result {
+                            Some(Ok(dir)) => Message::OutputDirUsed(dir),
+                            Some(Err(err)) => Message::Error(err),
+                            None => Message::Idle,
+                        }
+                    ),
+                    Some(Err(err)) => self.update(Message::Error(err.to_string())),
+                    None => Command::none(),
                 }
+            }
+            Message::ResourceScriptWritten(rc_path) => {
+                self.notice = Some(format!(
+                    "Icon and resource script written. Compile {} with rc.exe, windres or embed-resource.",
+                    rc_path.display()
+                ));
+                self.update(Message::OutputDirUsed(folder_of(&rc_path)))
+            }
+            Message::ToggleWriteRc(enabled) => {
+                self.write_rc = enabled;
+                Command::none()
+            }
+            Message::ProductNameChanged(value) => {
+                self.version_info.product_name = value;
+                Command::none()
+            }
+            Message::VersionChanged(value) => {
+                self.version_info.version = value;
+                Command::none()
+            }
+            Message::CompanyChanged(value) => {
+                self.version_info.company = value;
+                Command::none()
+            }
+            Message::PickCargoToml => {
+                let dir = self.settings.input_dir.clone();
+                Command::perform(
+                    async {
+                        tokio::task::spawn_blocking(move || {
+                            let path = file_dialog(dir).add_filter("Cargo.toml", &["toml"]).pick_file()?;
+                            let result = std::fs::read_to_string(&path)
+                                .map_err(rusty_svg2ico::Error::from)
+                                .and_then(|text| VersionInfo::from_cargo_toml(&text))
+                                .map_err(|err| format!("{}: {}", path.display(), err));
+                            Some(result)
+                        }).await.unwrap_or_else(|err| Some(Err(err.to_string())))
+                    },
+                    |result| match result {
+                        Some(Ok(version_info)) => Message::VersionInfoLoaded(version_info),
+                        Some(Err(err)) => Message::Error(err),
+                        None => Message::Idle,
+                    }
+                )
+            }
+            Message::VersionInfoLoaded(version_info) => {
+                self.version_info = version_info;
+                Command::none()
+            }
+            Message::WriteIntoExe => {
+                if let Some(data) = &self.ico_data {
+                    let data = data.clone();
+                    let input_dir = self.settings.input_dir.clone();
+                    Command::perform(
+                        async {
+                            tokio::task::spawn_blocking(move || {
+                                let exe_path = file_dialog(input_dir)
+                                    .set_title("Executable or DLL to put the icon into")
+                                    .add_filter("Executables and DLLs", pe::PE_EXTENSIONS)
+                                    .pick_file()?;
+                                let patched = std::fs::read(&exe_path)
+                                    .map_err(rusty_svg2ico::Error::from)
+                                    .and_then(|exe| pe::replace_icon(&exe, &data))
+                                    .map_err(|err| format!("{}: {}", exe_path.display(), err));
+                                let patched = match patched {
+                                    Ok(patched) => patched,
+                                    Err(err) => return Some(Err(err)),
+                                };
+                                // Offer the original name, but make overwriting it a deliberate choice
+                                let mut dialog = file_dialog(exe_path.parent().map(Path::to_path_buf))
+                                    .set_title("Save the new executable");
+                                if let Some(name) = exe_path.file_name() {
+                                    dialog = dialog.set_file_name(name.to_string_lossy());
+                                }
+                                let path = dialog.save_file()?;
+                                let entries = IconSet::from_ico_bytes(&data).map_or(0, |icon_set| icon_set.entries().len());
+                                let result = std::fs::write(&path, &patched).map_err(|err| format!("{}: {}", path.display(), err));
+                                Some(result.map(|()| (path, entries)))
+                            }).await.unwrap_or_else(|err| Some(Err(err.to_string())))
+                        },
+                        |result| match result {
+                            Some(Ok((path, entries))) => Message::ExeWritten(path, entries),
+                            Some(Err(err)) => Message::Error(err),
+                            None => Message::Idle,
+                        }
+                    )
+                } else {
+                    Command::none()
+                }
+            }
+            Message::ExeWritten(path, entries) => {
+                self.notice = Some(format!(
+                    "Icon with {} sizes written into {} and verified by reading it back.",
+                    entries,
+                    path.display()
+                ));
+                self.update(Message::OutputDirUsed(folder_of(&path)))
+            }
+            Message::ExportFavicons => {
+                if let Some(svg) = &self.svg_source {
+                    let svg = svg.clone();
+                    let name = self.source_stem();
+                    let options = self.convert_options();
+                    let output_dir = self.settings.output_dir.clone();
+                    Command::perform(
+                        async {
+                            tokio::task::spawn_blocking(move || {
+                                let dir = file_dialog(output_dir).set_title("Folder for the favicon files").pick_folder()?;
+                                let result = favicon::favicon_bundle(&svg, &options, &name)
+                                    .and_then(|bundle| bundle.write_to(&dir).map(|()| bundle.html))
+                                    .map(|html| (dir.clone(), html))
+                                    .map_err(|err| format!("{}: {}", dir.display(), err));
+                                Some(result)
+                            }).await.unwrap_or_else(|err| Some(Err(err.to_string())))
+                        },
+                        |result| match result {
+                            Some(Ok((dir, html))) => Message::FaviconsExported(dir, html),
+                            Some(Err(err)) => Message::Error(err),
+                            None => Message::Idle,
+                        }
+                    )
+                } else {
+                    Command::none()
+                }
+            }
+            Message::FaviconsExported(dir, html) => {
+                self.notice = Some(format!(
+                    "Favicons written to {}. The <link> tags are in favicon.html and on the clipboard.",
+                    dir.display()
+                ));
+                self.settings.output_dir = Some(dir);
+                self.save_settings();
+                iced::clipboard::write(html)
+            }
+            Message::OutputDirUsed(dir) => {
+                self.settings.output_dir = Some(dir);
+                self.save_settings();
+                Command::none()
+            }
+            Message::OpenRecent(RecentFile(path)) => {
+                if path.exists() {
+                    return self.open_file(path);
+                }
+                self.settings.recent_files.retain(|other| *other != path);
+                self.save_settings();
+                self.update(Message::Error(format!("{}: the file no longer exists", path.display())))
+            }
+            Message::ExportPng(index) => {
+                let png = self.ico_data.as_ref().map(|data| {
+                    IconSet::from_ico_bytes(data).and_then(|icon_set| {
+                        let name = icon_set.png_file_name(index, &self.source_stem())?;
+                        Ok((name, icon_set.entries()[index].to_png_bytes()?))
+                    })
+                });
+                let output_dir = self.settings.output_dir.clone();
+                match png {
+                    Some(Ok((name, data))) => Command::perform(
+                        async {
+                            tokio::task::spawn_blocking(move || {
+                                let path = file_dialog(output_dir).add_filter("PNG", &["png"]).set_file_name(name).save_file()?;
+                                let result = std::fs::write(&path, &data).map_err(|err| format!("{}: {}", path.display(), err));
+                                Some(result.map(|()| folder_of(&path)))
+                            }).await.unwrap_or_else(|err| Some(Err(err.to_string())))
+                        },
+                        |result| match result {
+                            Some(Ok(dir)) => Message::OutputDirUsed(dir),
+                            Some(Err(err)) => Message::Error(err),
+                            None => Message::Idle,
+                        }
+                    ),
+                    Some(Err(err)) => self.update(Message::Error(err.to_string())),
+                    None => Command::none(),
+                }
+            }
+            Message::ExportAllPngs => {
+                if let Some(data) = &self.ico_data {
+                    let data = data.clone();
+                    let stem = self.source_stem();
+                    let output_dir = self.settings.output_dir.clone();
+                    Command::perform(
+                        async {
+                            tokio::task::spawn_blocking(move || {
+                                let dir = file_dialog(output_dir).set_title("Folder for the PNG files").pick_folder()?;
+                                let result = IconSet::from_ico_bytes(&data)
+                                    .and_then(|icon_set| icon_set.write_pngs(&dir, &stem))
+                                    .map(|_| dir.clone())
+                                    .map_err(|err| format!("{}: {}", dir.display(), err));
+                                Some(result)
+                            }).await.unwrap_or_else(|err| Some(Err(err.to_string())))
+                        },
+                        |result| match result {
+                            Some(Ok(dir)) => Message::OutputDirUsed(dir),
+                            Some(Err(err)) => Message::Error(err),
+                            None => Message::Idle,
+                        }
+                    )
+                } else {
+                    Command::none()
+                }
+            }
+            Message::CheckCompatibility => {
+                match self.ico_data.as_ref().map(|data| lint::lint_ico(data)) {
+                    Some(Ok(findings)) => {
+                        self.lint_findings = Some(findings);
+                        self.batch_summary = None;
+                        self.pe_groups = None;
+                    }
+                    Some(Err(err)) => self.error = Some(err.to_string()),
+                    None => {}
+                }
+                Command::none()
+            }
+            Message::CloseCompatibility => {
+                self.lint_findings = None;
+                Command::none()
+            }
+            Message::ToggleHotspotMode(enabled) => {
+                self.hotspot_mode = enabled;
+                Command::none()
+            }
+            Message::TogglePerSizeHotspot(enabled) => {
+                self.per_size_hotspot = enabled;
+                Command::none()
+            }
+            Message::ToggleDetails(enabled) => {
+                self.show_details = enabled;
+                Command::none()
+            }
+            Message::OpenZoom(index) => {
+                self.zoom = Some(index);
+                self.hovered_pixel = None;
+                Command::none()
+            }
+            Message::CloseZoom => {
+                self.zoom = None;
+                Command::none()
+            }
+            Message::ZoomScaleChanged(scale) => {
+                self.zoom_scale = scale.round().clamp(2.0, 32.0);
+                Command::none()
+            }
+            Message::ToggleGrid(enabled) => {
+                self.zoom_grid = enabled;
+                Command::none()
+            }
+            Message::PixelHovered(pixel) => {
+                self.hovered_pixel = pixel;
+                Command::none()
+            }
+            Message::ThemeModeChanged(mode) => {
+                self.theme_mode = mode;
+                self.save_settings();
+                // Pick up the current OS theme right away instead of waiting for the next poll
+                if mode == ThemeMode::System {
+                    return self.update(Message::CheckSystemTheme);
+                }
+                Command::none()
+            }
+            Message::CheckSystemTheme => {
+                Command::perform(
+                    async {
+                        tokio::task::spawn_blocking(dark_light::detect).await.ok().and_then(Result::ok)
+                    },
+                    |mode| mode.map_or(Message::Idle, |mode| Message::SystemThemeDetected(mode == dark_light::Mode::Dark))
+                )
+            }
+            Message::SystemThemeDetected(is_dark) => {
+                self.system_dark = is_dark;
+                Command::none()
+            }
+            Message::BackgroundChanged(background) => {
+                self.preview_background = background;
+                Command::none()
+            }
+            Message::CustomBackgroundChanged(value) => {
+                if let Ok([r, g, b, a]) = rusty_svg2ico::parse_color(&value) {
+                    self.custom_color = Color::from_rgba8(r, g, b, f32::from(a) / 255.0);
+                }
+                self.custom_background = value;
+                Command::none()
+            }
+            Message::ToggleEditMode(enabled) => {
+                self.edit_mode = enabled;
+                Command::none()
+            }
+            Message::EditSizeChanged(value) => {
+                self.edit_size = value;
+                Command::none()
+            }
+            Message::PickEntryImage(edit) => {
+                // Replacements keep the size of the entry they replace; SVGs are rendered at that size,
+                // while PNGs have to be that size already
+                let size = match edit {
+                    EntryEdit::Add => match self.edit_size.trim().parse::<u32>() {
+                        Ok(size) if (1..=256).contains(&size) => size,
+                        _ => return self.update(Message::Error(format!("'{}' is not a valid icon size (expected 1-256)", self.edit_size))),
+                    },
+                    EntryEdit::Replace(index) => self.images.get(index).map_or(0, |preview| preview.width),
+                };
+                let options = self.convert_options();
+                let input_dir = self.settings.input_dir.clone();
+                Command::perform(
+                    async move {
+                        tokio::task::spawn_blocking(move || {
+                            let path = file_dialog(input_dir).add_filter("Images", &["png", "svg"]).pick_file()?;
+                            let is_svg = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("svg"));
+                            let result = std::fs::read(&path)
+                                .map_err(rusty_svg2ico::Error::from)
+                                .and_then(|data| {
+                                    if is_svg {
+                                        SvgRenderer::new(&data, &options)?.render(size)
+                                    } else {
+                                        Ok(::image::load_from_memory(&data)?.to_rgba8())
+                                    }
+                                })
+                                .map_err(|err| format!("{}: {}", path.display(), err))
+                                .and_then(|image| match edit {
+                                    // Replacements are checked against the entry once loaded
+                                    EntryEdit::Add if image.dimensions() != (size, size) => Err(format!(
+                                        "{}: the image is {} x {} but the new entry is {} x {}",
+                                        path.display(),
+                                        image.width(),
+                                        image.height(),
+                                        size,
+                                        size
+                                    )),
+                                    _ => Ok(image),
+                                });
+                            Some(result)
+                        }).await.unwrap_or_else(|err| Some(Err(err.to_string())))
+                    },
+                    move |result| match result {
+                        Some(Ok(image)) => Message::EntryImageLoaded(edit, image),
+                        Some(Err(err)) => Message::Error(err),
+                        None => Message::Idle,
+                    }
+                )
+            }
+            Message::EntryImageLoaded(EntryEdit::Replace(index), image)
+                if self.images.get(index).is_some_and(|preview| (preview.width, preview.height) != image.dimensions()) =>
+            {
+                // A hand-tuned bitmap must not be resampled, so the sizes have to match exactly
+                let preview = &self.images[index];
+                let message = format!(
+                    "The replacement is {} x {} but the entry is {} x {}",
+                    image.width(),
+                    image.height(),
+                    preview.width,
+                    preview.height
+                );
+                self.update(Message::Error(message))
+            }
+            Message::EntryImageLoaded(edit, image) => {
+                let encoding = self.convert_options().encoding;
+                self.edit_icon(|icon_set| {
+                    let entry = IconEntry::from_rgba(&image, encoding)?;
+                    match edit {
+                        EntryEdit::Add => {
+                            // Keep the largest-first order ICO files conventionally use
+                            let index = icon_set.entries().iter().position(|other| other.width() < entry.width()).unwrap_or(icon_set.len());
+                            icon_set.insert(index, entry)
+                        }
+                        EntryEdit::Replace(index) => icon_set.replace(index, entry).map(|_| ()),
+                    }
+                });
+                Command::none()
+            }
+            Message::MoveEntry(from, to) => {
+                self.edit_icon(|icon_set| icon_set.move_entry(from, to));
+                Command::none()
+            }
+            Message::RemoveEntry(index) => {
+                self.edit_icon(|icon_set| icon_set.remove(index).map(|_| ()));
+                Command::none()
+            }
+            Message::SetHotspot(index, x, y) => {
+                if self.per_size_hotspot {
+                    if let Some(hotspot) = self.hotspots.get_mut(index) {
+                        *hotspot = (x, y);
+                    }
+                } else if let Some(Ok(icon_set)) = self.ico_data.as_ref().map(|data| IconSet::from_ico_bytes(data)) {
+                    // Place the same spot of the artwork in every size
+                    self.hotspots = icon_set.scaled_hotspots(index, (x, y));
+                }
+                Command::none()
+            }
+            Message::BatchConvert => {
+                let options = self.convert_options();
+                let (input_dir, output_dir) = (self.settings.input_dir.clone(), self.settings.output_dir.clone());
+                Command::perform(
+                    async {
+                        tokio::task::spawn_blocking(move || {
+                            let input_dir = file_dialog(input_dir).set_title("Folder of SVG files").pick_folder()?;
+                            let output_dir = file_dialog(output_dir).set_title("Output folder for ICO files").pick_folder()?;
+                            let result = rusty_svg2ico::batch::convert_dir(&input_dir, &output_dir, &options)
+                                .map(|items| {
+                                    items
+                                        .into_iter()
+                                        .map(|item| (item.source, item.result.map_err(|err| err.to_string())))
+                                        .collect()
+                                })
+                                .map(|summary| (input_dir.clone(), output_dir, summary))
+                                .map_err(|err| format!("{}: {}", input_dir.display(), err));
+                            Some(result)
+                        }).await.unwrap_or_else(|err| Some(Err(err.to_string())))
+                    },
+                    |result| match result {
+                        Some(Ok((input_dir, output_dir, summary))) => Message::BatchFinished(input_dir, output_dir, summary),
+                        Some(Err(err)) => Message::Error(err),
+                        None => Message::Idle,
+                    }
+                )
+            }
+            Message::BatchFinished(input_dir, output_dir, summary) => {
+                self.settings.input_dir = Some(input_dir);
+                self.settings.output_dir = Some(output_dir);
+                self.save_settings();
+                self.batch_summary = Some(summary);
+                self.lint_findings = None;
+                self.pe_groups = None;
+                Command::none()
+            }
+            Message::IcoLoaded(path, data, svg_source) => {
+                // On failure the previously loaded icon stays on screen
+                match self.load_images(&data) {
+                    Ok(()) => {
+                        if svg_source.is_some() {
+                            self.mark_sources(&path);
+                        }
+                        self.ico_data = Some(data);
+                        self.source_path = Some(path.clone());
+                        self.svg_source = svg_source;
+                        self.error = None;
+                        self.notice = None;
+                        self.batch_summary = None;
+                        self.lint_findings = None;
+                        self.pe_groups = None;
+                        self.settings.add_recent_file(&path);
+                        self.save_settings();
+                    }
+                    Err(err) => self.error = Some(err.to_string()),
+                }
+                Command::none()
+            }
+            Message::RasterConverted(path, data, warning) => {
+                // Raster sources cannot be re-rendered, so ICNS and favicon export stay SVG-only
+                let command = self.update(Message::IcoLoaded(path.clone(), data, None));
+                if self.error.is_none() {
+                    self.mark_sources(&path);
+                    self.notice = warning.map(|warning| format!("Warning: {}", warning));
+                }
+                command
+            }
+            Message::IcnsLoaded(path, entries) => {
+                // ICNS files are view-only: there is no ICO to save
+                self.images = entries
+                    .into_iter()
+                    .map(|entry| PreviewImage {
+                        label: format!("{} x {} ({})", entry.image.width(), entry.image.height(), entry.kind),
+                        width: entry.image.width(),
+                        height: entry.image.height(),
+                        handle: Some(image_handle(entry.image.clone())),
+                        rgba: Some(entry.image),
+                        source: None,
+                    })
+                    .collect();
+                self.hotspots = vec![(0, 0); self.images.len()];
+                self.entry_info.clear();
+                self.zoom = None;
+                self.hotspot_mode = false;
+                self.ico_data = None;
+                self.source_path = None;
+                self.svg_source = None;
+                self.error = None;
+                self.notice = None;
+                self.batch_summary = None;
+                self.lint_findings = None;
+                self.pe_groups = None;
+                self.settings.add_recent_file(&path);
+                self.save_settings();
+                Command::none()
+            }
+            Message::Error(err) => {
+                self.error = Some(err);
+                Command::none()
+            }
+            Message::DismissError => {
+                self.error = None;
+                Command::none()
+            }
+            Message::DismissNotice => {
+                self.notice = None;
+                Command::none()
+            }
+            Message::FileHovered => {
+                self.is_file_hovered = true;
+                Command::none()
+            }
+            Message::FilesHoveredLeft => {
+                self.is_file_hovered = false;
+                Command::none()
+            }
+            Message::FileDropped(path) => {
+                self.is_file_hovered = false;
+                self.open_file(path)
+            }
+            Message::WindowResized(width, height) => {
+                // Minimizing reports a zero size on some platforms
+                if width > 0 && height > 0 {
+                    self.settings.window_size = Some((width as f32, height as f32));
+                }
+                Command::none()
+            }
+            Message::CloseRequested => {
+                // The window size is only written here rather than on every resize event
+                self.save_settings();
+                window::close(window::Id::MAIN)
+            }
+            Message::Idle => Command::none(),
+            Message::ToggleSize(size, checked) => {
+                if let Some(entry) = self.sizes.iter_mut().find(|(s, _)| *s == size) {
+                    entry.1 = checked;
+                }
+                self.save_settings();
+                Command::none()
+            }
+            Message::FilterChanged(filter) => {
+                self.filter = filter;
+                Command::none()
+            }
+            Message::OverrideSizeChanged(value) => {
+                self.override_size = value;
+                Command::none()
+            }
+            Message::PickOverride => {
+                let size = match self.override_size.trim().parse::<u16>() {
+                    Ok(size) if (1..=256).contains(&size) => size,
+                    _ => return self.update(Message::Error(format!("'{}' is not a valid icon size (expected 1-256)", self.override_size))),
+                };
+                let input_dir = self.settings.input_dir.clone();
+                Command::perform(
+                    async move {
+                        tokio::task::spawn_blocking(move || {
+                            file_dialog(input_dir).add_filter("Images", &["svg", "png"]).pick_file()
+                        }).await.ok().flatten()
+                    },
+                    move |path_opt| path_opt.map_or(Message::Idle, |path| Message::OverridePicked(size, path))
+                )
+            }
+            Message::OverridePicked(size, path) => {
+                self.overrides.retain(|(other, _)| *other != size);
+                self.settings.input_dir = path.parent().map(Path::to_path_buf);
+                self.save_settings();
+                self.overrides.push((size, path));
+                self.overrides.sort_by_key(|(size, _)| std::cmp::Reverse(*size));
+                Command::none()
+            }
+            Message::RemoveOverride(size) => {
+                self.overrides.retain(|(other, _)| *other != size);
+                Command::none()
+            }
+            Message::CustomSizeChanged(value) => {
+                self.custom_size = value;
+                Command::none()
+            }
+            Message::AddCustomSize => {
+                let sizes = match rusty_svg2ico::parse_sizes(&self.custom_size) {
+                    Ok(sizes) => sizes,
+                    Err(err) => return self.update(Message::Error(err.to_string())),
+                };
+                for size in sizes {
+                    match self.sizes.iter_mut().find(|(s, _)| *s == size) {
+                        Some(entry) => entry.1 = true,
+                        None => self.sizes.push((size, true)),
+                    }
+                }
+                // Keep the checkboxes ordered largest first, matching the ICO entry order
+                self.sizes.sort_by_key(|(size, _)| std::cmp::Reverse(*size));
+                self.custom_size.clear();
+                self.save_settings();
                 Command::none()
             }
         }
     }
 
+    fn theme(&self) -> Theme {
+        if self.is_dark() { Theme::Dark } else { Theme::Light }
+    }
+
+    fn subscription(&self) -> Subscription<Message> {
+        let file_drops = event::listen_with(|event, _status| match event {
+            Event::Window(_, window::Event::FileHovered(_)) => Some(Message::FileHovered),
+            Event::Window(_, window::Event::FilesHoveredLeft) => Some(Message::FilesHoveredLeft),
+            Event::Window(_, window::Event::FileDropped(path)) => Some(Message::FileDropped(path)),
+            Event::Window(_, window::Event::Resized { width, height }) => Some(Message::WindowResized(width, height)),
+            Event::Window(_, window::Event::CloseRequested) => Some(Message::CloseRequested),
+            _ => None,
+        });
+        // The OS gives no portable change notification, so follow it by polling
+        let system_theme = (self.theme_mode == ThemeMode::System)
+            .then(|| iced::time::every(THEME_POLL_INTERVAL).map(|_| Message::CheckSystemTheme));
+        Subscription::batch([file_drops].into_iter().chain(system_theme))
+    }
+
     fn view(&self) -> Element<'_, Message> {
+        // Colors of everything drawn directly on the window background
+        let (main_bg_color, label_color) = if self.is_dark() {
+            (Color::from_rgb(48.0 / 255.0, 48.0 / 255.0, 48.0 / 255.0), Color::WHITE) // #303030
+        } else {
+            (Color::from_rgb(240.0 / 255.0, 240.0 / 255.0, 240.0 / 255.0), Color::BLACK) // #F0F0F0
+        };
+        let label = iced::theme::Text::Color(label_color);
+
         let logo = container(image(self.logo.as_ref().unwrap().clone()).width(Length::Fixed(200.0)))
             .width(Length::Fill)
             .center_x()
@@ -182,54 +1520,383 @@ impl Application for SvgToIcoApp {
             .style(iced::theme::Container::Custom(Box::new(LogoStyle)))
             .padding([0, 20, 10, 20]); // top 0, right 20, bottom 10, left 20
 
-        let select_button = button("Select SVG File").on_press(Message::SelectSvg);
+        let has_sizes = self.sizes.iter().any(|(_, checked)| *checked);
+        let select_button = button("Select Image File").on_press_maybe(has_sizes.then_some(Message::SelectSvg));
         let open_button = button("Open ICO File").on_press(Message::OpenIco);
+        let batch_button = button("Convert Folder").on_press_maybe(has_sizes.then_some(Message::BatchConvert));
 
-        let buttons_row = row![select_button, open_button].spacing(10);
+        let pe_button = button("Open from EXE/DLL").on_press(Message::OpenPe);
 
-        let save_button = if self.ico_data.is_some() && self.is_generated {
-            Some(button("Save Icon").on_press(Message::SaveIcon))
+        let buttons_row = column![row![select_button, open_button, batch_button].spacing(10), pe_button]
+            .spacing(6)
+            .align_items(Alignment::Center);
+        let recent_files: Vec<RecentFile> = self.settings.recent_files.iter().cloned().map(RecentFile).collect();
+        let theme_row = row![
+            pick_list(recent_files, None::<RecentFile>, Message::OpenRecent)
+                .placeholder("Open recent file")
+                .text_size(14)
+                .width(Length::Fixed(200.0)),
+            text("Theme").style(label),
+            pick_list(&ThemeMode::ALL[..], Some(self.theme_mode), Message::ThemeModeChanged).text_size(14),
+        ]
+        .spacing(10)
+        .align_items(Alignment::Center);
+
+        let mut sizes_column = column![].spacing(6);
+        for chunk in self.sizes.chunks(6) {
+            let mut sizes_row = row![].spacing(10);
+            for &(size, checked) in chunk {
+                sizes_row = sizes_row.push(
+                    checkbox(size.to_string(), checked)
+                        .on_toggle(move |checked| Message::ToggleSize(size, checked))
+                        .size(16)
+                        .spacing(4)
+                        .style(iced::theme::Checkbox::Custom(Box::new(SizeCheckboxStyle(label_color)))),
+                );
+            }
+            sizes_column = sizes_column.push(sizes_row);
+        }
+        let custom_size_row = row![
+            text_input("Custom sizes, e.g. 20,40", &self.custom_size)
+                .on_input(Message::CustomSizeChanged)
+                .on_submit(Message::AddCustomSize)
+                .width(Length::Fixed(200.0)),
+            button("Add").on_press(Message::AddCustomSize),
+        ]
+        .spacing(10)
+        .align_items(Alignment::Center);
+        let override_row = row![
+            text_input("Size", &self.override_size)
+                .on_input(Message::OverrideSizeChanged)
+                .on_submit(Message::PickOverride)
+                .width(Length::Fixed(60.0)),
+            button("Use Other SVG/PNG for Size").on_press(Message::PickOverride),
+        ]
+        .spacing(10)
+        .align_items(Alignment::Center);
+        let filter_row = row![
+            text("Scaling filter for PNG/JPEG sources").size(14).style(label),
+            pick_list(&RESIZE_FILTERS[..], Some(self.filter), Message::FilterChanged).text_size(14),
+        ]
+        .spacing(10)
+        .align_items(Alignment::Center);
+        let mut sizes_column = sizes_column.push(custom_size_row).push(filter_row).push(override_row);
+        for (size, path) in &self.overrides {
+            let name = path.file_name().map_or_else(|| path.display().to_string(), |name| name.to_string_lossy().into_owned());
+            sizes_column = sizes_column.push(
+                row![
+                    text(format!("{} px: {}", size, name)).size(12).style(label),
+                    button(text("Remove").size(12)).padding([2, 6]).on_press(Message::RemoveOverride(*size)),
+                ]
+                .spacing(10)
+                .align_items(Alignment::Center),
+            );
+        }
+
+        let error_banner = self.error.as_ref().map(|err| {
+            container(
+                row![
+                    text(err).width(Length::Fill),
+                    button("Dismiss").on_press(Message::DismissError),
+                ]
+                .spacing(10)
+                .align_items(Alignment::Center),
+            )
+            .width(Length::Fixed(380.0))
+            .padding(8)
+            .style(iced::theme::Container::Custom(Box::new(ErrorBannerStyle)))
+        });
+
+        let notice_banner = self.notice.as_ref().map(|notice| {
+            container(
+                row![
+                    text(notice).width(Length::Fill),
+                    button("Dismiss").on_press(Message::DismissNotice),
+                ]
+                .spacing(10)
+                .align_items(Alignment::Center),
+            )
+            .width(Length::Fixed(380.0))
+            .padding(8)
+            .style(iced::theme::Container::Custom(Box::new(NoticeBannerStyle)))
+        });
+
+        let save_button = if self.ico_data.is_some() {
+            let is_generated = self.svg_source.is_some();
+            let save_row = row![button("Save Icon").on_press(Message::SaveIcon)]
+                .push_maybe(is_generated.then(|| button("Save ICNS").on_press(Message::SaveIcns)))
+                .push(button("Save as Cursor").on_press(Message::SaveCursor))
+                .push(button("Write into EXE").on_press(Message::WriteIntoExe))
+                .spacing(10);
+            let export_row = row![]
+                .push_maybe(is_generated.then(|| button("Favicons").on_press(Message::ExportFavicons)))
+                .push(button("Export All PNGs").on_press(Message::ExportAllPngs))
+                .push(button("Check").on_press(Message::CheckCompatibility))
+                .spacing(10);
+            let write_rc = checkbox("Also write .rc with version info", self.write_rc)
+                .on_toggle(Message::ToggleWriteRc)
+                .size(16)
+                .style(iced::theme::Checkbox::Custom(Box::new(SizeCheckboxStyle(label_color))));
+            let version_form = self.write_rc.then(|| {
+                column![
+                    row![
+                        text_input("Product name", &self.version_info.product_name)
+                            .on_input(Message::ProductNameChanged)
+                            .width(Length::Fixed(180.0)),
+                        text_input("Version", &self.version_info.version)
+                            .on_input(Message::VersionChanged)
+                            .width(Length::Fixed(80.0)),
+                    ]
+                    .spacing(10),
+                    row![
+                        text_input("Company", &self.version_info.company)
+                            .on_input(Message::CompanyChanged)
+                            .width(Length::Fixed(180.0)),
+                        button("From Cargo.toml").on_press(Message::PickCargoToml),
+                    ]
+                    .spacing(10)
+                    .align_items(Alignment::Center),
+                ]
+                .spacing(6)
+            });
+            Some(column![save_row, export_row, write_rc].push_maybe(version_form).spacing(6).align_items(Alignment::Center))
         } else {
             None
         };
 
-        let images_column = if self.images.is_empty() {
+        let hotspot_controls = self.ico_data.is_some().then(|| {
+            let mode = checkbox("Edit cursor hotspot", self.hotspot_mode)
+                .on_toggle(Message::ToggleHotspotMode)
+                .size(16)
+                .style(iced::theme::Checkbox::Custom(Box::new(SizeCheckboxStyle(label_color))));
+            let per_size = self.hotspot_mode.then(|| {
+                checkbox("Per-size", self.per_size_hotspot)
+                    .on_toggle(Message::TogglePerSizeHotspot)
+                    .size(16)
+                    .style(iced::theme::Checkbox::Custom(Box::new(SizeCheckboxStyle(label_color))))
+            });
+            let details = checkbox("Entry details", self.show_details)
+                .on_toggle(Message::ToggleDetails)
+                .size(16)
+                .style(iced::theme::Checkbox::Custom(Box::new(SizeCheckboxStyle(label_color))));
+            let edit = checkbox("Edit entries", self.edit_mode)
+                .on_toggle(Message::ToggleEditMode)
+                .size(16)
+                .style(iced::theme::Checkbox::Custom(Box::new(SizeCheckboxStyle(label_color))));
+            let add_row = self.edit_mode.then(|| {
+                row![
+                    text_input("Size", &self.edit_size)
+                        .on_input(Message::EditSizeChanged)
+                        .width(Length::Fixed(60.0)),
+                    button("Add PNG / SVG").on_press(Message::PickEntryImage(EntryEdit::Add)),
+                ]
+                .spacing(10)
+                .align_items(Alignment::Center)
+            });
+            column![
+                row![mode].push_maybe(per_size).spacing(20),
+                row![details, edit].spacing(20),
+            ]
+            .push_maybe(add_row)
+            .spacing(6)
+            .align_items(Alignment::Center)
+        });
+
+        let container_bg_color = match self.preview_background {
+            // Behind the checkerboard squares, the frame keeps the theme color
+            PreviewBackground::Checkerboard if self.is_dark() => Color::from_rgb(128.0 / 255.0, 128.0 / 255.0, 128.0 / 255.0), // grey
+            PreviewBackground::Checkerboard => Color::from_rgb(1.0, 1.0, 240.0 / 255.0), // ivory
+            PreviewBackground::White => Color::WHITE,
+            PreviewBackground::Black => Color::BLACK,
+            PreviewBackground::TaskbarLight => Color::from_rgb8(0xF3, 0xF3, 0xF3),
+            PreviewBackground::TaskbarDark => Color::from_rgb8(0x20, 0x20, 0x20),
+            PreviewBackground::Custom => self.custom_color,
+        };
+        let preview_text_color = text_color_on(container_bg_color);
+        let preview_text = iced::theme::Text::Color(preview_text_color);
+        let checkerboard = self.preview_background == PreviewBackground::Checkerboard;
+
+        let background_row = row![
+            text("Background").style(label),
+            pick_list(&PreviewBackground::ALL[..], Some(self.preview_background), Message::BackgroundChanged).text_size(14),
+        ]
+        .push_maybe((self.preview_background == PreviewBackground::Custom).then(|| {
+            text_input("RRGGBB", &self.custom_background)
+                .on_input(Message::CustomBackgroundChanged)
+                .width(Length::Fixed(80.0))
+        }))
+        .spacing(10)
+        .align_items(Alignment::Center);
+
+        let images_column = if let Some(list) = &self.pe_groups {
+            let name = list.path.file_name().map_or_else(|| list.path.display().to_string(), |name| name.to_string_lossy().into_owned());
+            let mut col = column![row![
+                text(format!("{} icon groups in {}", list.groups.len(), name)).width(Length::Fill).style(preview_text),
+                button(text("Close").size(12)).padding([2, 6]).on_press(Message::ClosePeGroups),
+            ]
+            .align_items(Alignment::Center)]
+            .spacing(10);
+            for (index, (_, summary, thumbnail)) in list.groups.iter().enumerate() {
+                let thumbnail = thumbnail.as_ref().map(|handle| container(image(handle.clone())).width(Length::Fixed(48.0)).center_x());
+                col = col.push(
+                    row![]
+                        .push_maybe(thumbnail)
+                        .push(text(summary).size(12).width(Length::Fill).style(preview_text))
+                        .push(button(text("Open").size(12)).padding([2, 6]).on_press(Message::OpenPeGroup(index)))
+                        .spacing(10)
+                        .align_items(Alignment::Center),
+                );
+            }
+            col
+        } else if let Some(findings) = &self.lint_findings {
+            let count = |severity| findings.iter().filter(|finding| finding.severity == severity).count();
+            let heading = if findings.is_empty() {
+                "No Windows compatibility problems found".to_string()
+            } else {
+                format!(
+                    "{} errors, {} warnings, {} notes",
+                    count(Severity::Error),
+                    count(Severity::Warning),
+                    count(Severity::Info)
+                )
+            };
+            let mut col = column![row![
+                text(heading).width(Length::Fill).style(preview_text),
+                button(text("Close").size(12)).padding([2, 6]).on_press(Message::CloseCompatibility),
+            ]
+            .align_items(Alignment::Center)]
+            .spacing(6);
+            for finding in findings {
+                let color = match finding.severity {
+                    Severity::Error => Color::from_rgb(160.0 / 255.0, 40.0 / 255.0, 40.0 / 255.0), // #A02828
+                    Severity::Warning => Color::from_rgb(176.0 / 255.0, 112.0 / 255.0, 0.0), // #B07000
+                    Severity::Info => preview_text_color,
+                };
+                col = col.push(text(finding.to_string()).size(12).style(iced::theme::Text::Color(color)));
+            }
+            col
+        } else if let Some(summary) = &self.batch_summary {
+            let failed = summary.iter().filter(|(_, result)| result.is_err()).count();
+            let mut col = column![text(format!("{} converted, {} failed", summary.len() - failed, failed))
+                .style(preview_text)]
+            .spacing(6);
+            for (path, result) in summary {
+                let line = match result {
+                    Ok(()) => format!("OK  {}", path.display()),
+                    Err(err) => format!("FAILED  {}: {}", path.display(), err),
+                };
+                col = col.push(text(line).size(12).style(preview_text));
+            }
+            col
+        } else if self.images.is_empty() {
             column![].height(Length::Fixed(400.0))
         } else {
             let mut col = column![].spacing(10);
-            for (handle, res) in &self.images {
-                let img = image(handle.clone());
-                let txt = text(res).style(iced::theme::Text::Color(Color::WHITE));
-                let txt_container = container(txt).width(Length::Fill).align_x(alignment::Horizontal::Right);
-                col = col.push(row![img, txt_container].spacing(10).align_items(Alignment::Center));
+            for (index, preview) in self.images.iter().enumerate() {
+                let img: Option<Element<'_, Message>> = preview.handle.as_ref().map(|handle| {
+                    if self.hotspot_mode {
+                        PixelView::new(handle.clone(), preview.width, preview.height)
+                            .checkerboard(checkerboard)
+                            .marker(self.hotspots.get(index).copied())
+                            .on_press(move |x, y| Message::SetHotspot(index, x, y))
+                            .into()
+                    } else {
+                        mouse_area(PixelView::new(handle.clone(), preview.width, preview.height).checkerboard(checkerboard))
+                            .on_press(Message::OpenZoom(index))
+                            .interaction(mouse::Interaction::Pointer)
+                            .into()
+                    }
+                });
+                let mut res = match self.hotspots.get(index) {
+                    Some((x, y)) if self.hotspot_mode => format!("{}\nhotspot {}, {}", preview.label, x, y),
+                    _ => preview.label.clone(),
+                };
+                if let Some(source) = &preview.source {
+                    res = format!("{}\nfrom {}", res, source);
+                }
+                let txt = text(res).style(preview_text);
+                let details = self
+                    .entry_info
+                    .get(index)
+                    .filter(|_| self.show_details)
+                    .map(|info| text(entry_details(info)).size(12).style(preview_text));
+                // ICNS previews have no ICO entries to export
+                let small_button = |label| button(text(label).size(12)).padding([2, 6]);
+                let export = self.ico_data.is_some().then(|| small_button("Export PNG").on_press(Message::ExportPng(index)));
+                let zoom = preview.handle.is_some().then(|| small_button("Zoom").on_press(Message::OpenZoom(index)));
+                let entry_buttons = row![].push_maybe(zoom).push_maybe(export).spacing(4);
+                let edit_buttons = (self.edit_mode && self.ico_data.is_some()).then(|| {
+                    let last = self.images.len() - 1;
+                    column![
+                        row![
+                            small_button("Up").on_press_maybe((index > 0).then(|| Message::MoveEntry(index, index - 1))),
+                            small_button("Down").on_press_maybe((index < last).then(|| Message::MoveEntry(index, index + 1))),
+                        ]
+                        .spacing(4),
+                        row![
+                            small_button("Replace").on_press(Message::PickEntryImage(EntryEdit::Replace(index))),
+                            // An ICO needs at least one entry
+                            small_button("Remove").on_press_maybe((last > 0).then_some(Message::RemoveEntry(index))),
+                        ]
+                        .spacing(4),
+                    ]
+                    .spacing(4)
+                    .align_items(Alignment::End)
+                });
+                let txt_container = container(
+                    column![txt]
+                        .push_maybe(details)
+                        .push(entry_buttons)
+                        .push_maybe(edit_buttons)
+                        .spacing(4)
+                        .align_items(Alignment::End),
+                )
+                    .width(Length::Fill)
+                    .align_x(alignment::Horizontal::Right);
+                col = col.push(row![].push_maybe(img).push(txt_container).spacing(10).align_items(Alignment::Center));
             }
             col
         };
 
-        let scrollable_images = scrollable(images_column).height(Length::Fixed(646.0)).width(Length::Fixed(380.0));
-
-        let container_bg_color = if self.is_dark {
-            Color::from_rgb(128.0 / 255.0, 128.0 / 255.0, 128.0 / 255.0) // grey
-        } else {
-            Color::from_rgb(1.0, 1.0, 240.0 / 255.0) // ivory
+        let scrollable_images: Element<'_, Message> = match self.zoom.and_then(|index| Some((index, self.images.get(index)?))) {
+            Some((index, preview)) => self.zoom_view(index, preview, preview_text_color),
+            None => scrollable(images_column).height(Length::Fill).width(Length::Fixed(380.0)).into(),
         };
+
         let framed_images = container(scrollable_images)
-            .height(Length::Fixed(646.0))
-            .style(iced::theme::Container::Custom(Box::new(MyContainerStyle(container_bg_color))))
+            .height(Length::Fill)
+            .style(if self.is_file_hovered {
+                iced::theme::Container::Custom(Box::new(DropTargetStyle(container_bg_color)))
+            } else {
+                iced::theme::Container::Custom(Box::new(MyContainerStyle(container_bg_color)))
+            })
             .padding(6);
 
-        let mut content = column![logo, buttons_row]
+        // The preview takes whatever height the controls above it leave free
+        let mut content = column![logo, buttons_row, theme_row, sizes_column]
             .spacing(10)
+            .height(Length::Fill)
+            .padding([0, 0, 10, 0])
             .align_items(Alignment::Center);
 
         if let Some(save) = save_button {
             content = content.push(save);
         }
 
-        content = content.push(vertical_space().height(6));
-        content = content.push(framed_images);
+        if let Some(controls) = hotspot_controls {
+            content = content.push(controls);
+        }
+
+        if let Some(banner) = error_banner {
+            content = content.push(banner);
+        }
+
+        if let Some(banner) = notice_banner {
+            content = content.push(banner);
+        }
 
-        let main_bg_color = Color::from_rgb(48.0 / 255.0, 48.0 / 255.0, 48.0 / 255.0); // #303030
+        content = content.push(background_row);
+        content = content.push(framed_images);
 
         container(content)
             .width(Length::Fill)
@@ -242,13 +1909,23 @@ impl Application for SvgToIcoApp {
 }
 
 fn main() -> iced::Result {
+    // Any arguments select the headless command-line mode
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        std::process::exit(cli::run(&args));
+    }
+
     let is_dark = dark_light::detect().unwrap_or(dark_light::Mode::Light) == dark_light::Mode::Dark;
-    let icon = iced::window::icon::from_file("rustysvg2ico.ico").ok();
+    let settings = AppSettings::load();
+    let (width, height) = settings.window_size.unwrap_or((420.0, 868.0));
+    let icon = iced::window::icon::from_file_data(include_bytes!("../assets/rustysvg2ico.ico"), None).ok();
     SvgToIcoApp::run(Settings {
-        flags: is_dark,
+        flags: (is_dark, settings),
         window: window::Settings {
-            size: Size::new(420.0, 868.0),
+            size: Size::new(width, height),
             icon,
+            // Closing goes through Message::CloseRequested so the settings are saved first
+            exit_on_close_request: false,
             ..Default::default()
         },
         ..Default::default()
@@ -0,0 +1,851 @@
+//! Reads and replaces the icon resources of Windows executables and DLLs (PE files) without any
+//! Windows APIs.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::io;
+
+use crate::bytes::{u16_le, u32_le};
+use crate::Error;
+
+/// File extensions of the PE files the icon readers are offered for.
+pub const PE_EXTENSIONS: &[&str] = &["exe", "dll", "ocx", "cpl", "scr"];
+
+/// Resource type of a single icon image.
+pub const RT_ICON: u16 = 3;
+/// Resource type of an icon group: the directory that ties several `RT_ICON` images into one icon.
+pub const RT_GROUP_ICON: u16 = 14;
+
+const RESOURCE_DIRECTORY_INDEX: usize = 2;
+const CERTIFICATE_DIRECTORY_INDEX: usize = 4;
+const SECTION_HEADER_LEN: usize = 40;
+const GROUP_ENTRY_LEN: usize = 14;
+// Far more than any real executable has; a corrupt directory tree cannot make more copies than this
+const MAX_RESOURCES: usize = 0x10000;
+
+/// The name or numeric ID of a resource.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ResourceName {
+    Id(u16),
+    Name(String),
+}
+
+impl fmt::Display for ResourceName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResourceName::Id(id) => write!(f, "#{}", id),
+            ResourceName::Name(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+/// One icon group of a PE file, reassembled into a standalone ICO file.
+#[derive(Debug, Clone)]
+pub struct IconGroup {
+    pub name: ResourceName,
+    /// Windows language ID of the group, e.g. 1033 for US English; 0 for language-neutral.
+    pub language: u16,
+    /// The group's images as an ICO file.
+    pub ico: Vec<u8>,
+}
+
+/// Lists every icon group in an EXE or DLL, in resource order.
+///
+/// The application icon Explorer shows is the first group. Images a group refers to but the file
+/// does not contain are left out of its ICO; groups with no images at all are skipped.
+pub fn read_icon_groups(data: &[u8]) -> Result<Vec<IconGroup>, Error> {
+    let resources = read_resources(data)?;
+    let mut groups = Vec::new();
+    for group in resources.iter().filter(|resource| resource.kind == ResourceName::Id(RT_GROUP_ICON)) {
+        let find_icon = |id: u16| {
+            let icons = resources
+                .iter()
+                .filter(|resource| resource.kind == ResourceName::Id(RT_ICON) && resource.name == ResourceName::Id(id));
+            // Prefer the image in the group's own language, as Windows does
+            let mut icons = icons.clone().filter(|icon| icon.language == group.language).chain(icons);
+            icons.next()
+        };
+        let dir = &group.data;
+        if dir.len() < 6 {
+            continue;
+        }
+        let count = u16_le(dir, 4) as usize;
+        let mut entries = Vec::with_capacity(count);
+        for index in 0..count {
+            let Some(entry) = dir.get(6 + index * GROUP_ENTRY_LEN..6 + (index + 1) * GROUP_ENTRY_LEN) else {
+                break;
+            };
+            if let Some(icon) = find_icon(u16_le(entry, 12)) {
+                entries.push((&entry[..8], &icon.data));
+            }
+        }
+        if entries.is_empty() {
+            continue;
+        }
+        groups.push(IconGroup { name: group.name.clone(), language: group.language, ico: build_ico(&entries) });
+    }
+    Ok(groups)
+}
+
+/// Replaces the application icon of an EXE or DLL with an ICO file and returns the patched file.
+///
+/// The first icon group, the one Explorer shows, is rewritten in place keeping its name and
+/// language, and its old images are dropped; a file without icons gets a new group `#1`. The
+/// rebuilt resources replace the resource section when it is the last one in the file and holds
+/// nothing else, and go into a new section appended to the file otherwise, leaving the old one in place.
+/// An Authenticode signature no longer matches the patched file and is removed.
+///
+/// The result is read back before it is returned, so an `Ok` file is known to carry the icon.
+pub fn replace_icon(exe: &[u8], ico: &[u8]) -> Result<Vec<u8>, Error> {
+    let images = read_ico_images(ico)?;
+    let headers = read_headers(exe)?;
+    let mut resources = read_resources(exe)?;
+
+    let is_group = |resource: &Resource| resource.kind == ResourceName::Id(RT_GROUP_ICON);
+    let first_group = resources.iter().position(is_group);
+    let (group_name, language) = match first_group {
+        Some(index) => (resources[index].name.clone(), resources[index].language),
+        None => (ResourceName::Id(1), 0),
+    };
+    // Images of the old group go away unless another group uses them too
+    let old_ids = first_group.map(|index| group_icon_ids(&resources[index].data)).unwrap_or_default();
+    let shared_ids: Vec<u16> = resources
+        .iter()
+        .enumerate()
+        .filter(|&(index, resource)| Some(index) != first_group && is_group(resource))
+        .flat_map(|(_, resource)| group_icon_ids(&resource.data))
+        .collect();
+    let is_removed = |resource: &Resource| {
+        resource.kind == ResourceName::Id(RT_ICON)
+            && matches!(resource.name, ResourceName::Id(id) if old_ids.contains(&id) && !shared_ids.contains(&id))
+    };
+    let mut used_ids: Vec<u16> = resources
+        .iter()
+        .filter(|resource| resource.kind == ResourceName::Id(RT_ICON) && !is_removed(resource))
+        .filter_map(|resource| match resource.name {
+            ResourceName::Id(id) => Some(id),
+            ResourceName::Name(_) => None,
+        })
+        .collect();
+
+    let mut group_dir = vec![0, 0, 1, 0];
+    group_dir.extend_from_slice(&(images.len() as u16).to_le_bytes());
+    let mut new_icons = Vec::with_capacity(images.len());
+    let mut next_id = 1;
+    for (header, data) in &images {
+        while used_ids.contains(&next_id) {
+            next_id += 1;
+        }
+        used_ids.push(next_id);
+        group_dir.extend_from_slice(header);
+        group_dir.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        group_dir.extend_from_slice(&next_id.to_le_bytes());
+        new_icons.push(Resource {
+            kind: ResourceName::Id(RT_ICON),
+            name: ResourceName::Id(next_id),
+            language,
+            code_page: 0,
+            data: data.to_vec(),
+        });
+    }
+    let group = Resource { kind: ResourceName::Id(RT_GROUP_ICON), name: group_name.clone(), language, code_page: 0, data: group_dir };
+    match first_group {
+        Some(index) => resources[index] = group,
+        None => resources.push(group),
+    }
+    resources.retain(|resource| !is_removed(resource));
+    resources.extend(new_icons);
+
+    let patched = write_resource_section(exe, &headers, &resources)?;
+
+    let written = read_icon_groups(&patched)?
+        .into_iter()
+        .find(|group| group.name == group_name && group.language == language)
+        .ok_or_else(|| invalid("the icon group is missing when the patched file is read back"))?;
+    let written_images = read_ico_images(&written.ico)?;
+    if written_images != images {
+        return Err(invalid("the icon read back from the patched file does not match"));
+    }
+    Ok(patched)
+}
+
+// The RT_ICON IDs an RT_GROUP_ICON directory refers to
+fn group_icon_ids(dir: &[u8]) -> Vec<u16> {
+    let count = if dir.len() >= 6 { u16_le(dir, 4) as usize } else { 0 };
+    (0..count)
+        .map_while(|index| dir.get(6 + index * GROUP_ENTRY_LEN..6 + (index + 1) * GROUP_ENTRY_LEN))
+        .map(|entry| u16_le(entry, 12))
+        .collect()
+}
+
+// An ICO directory entry without its size and offset, as RT_GROUP_ICON stores it, and the image
+type IcoImage<'a> = ([u8; 8], &'a [u8]);
+
+// Splits an ICO file into the first 8 bytes of each directory entry and its image data
+fn read_ico_images(ico: &[u8]) -> Result<Vec<IcoImage<'_>>, Error> {
+    let invalid_ico = |msg: &str| Error::InvalidIco(io::Error::new(io::ErrorKind::InvalidData, msg.to_string()));
+    if ico.len() < 6 || u16_le(ico, 0) != 0 {
+        return Err(invalid_ico("not an ICO file"));
+    }
+    if u16_le(ico, 2) != 1 {
+        return Err(invalid_ico("cursors cannot be application icons"));
+    }
+    let count = u16_le(ico, 4) as usize;
+    let mut images = Vec::with_capacity(count);
+    for index in 0..count {
+        let entry = ico.get(6 + index * 16..6 + (index + 1) * 16).ok_or_else(|| invalid_ico("directory is truncated"))?;
+        let (size, offset) = (u32_le(entry, 8) as usize, u32_le(entry, 12) as usize);
+        let data = offset
+            .checked_add(size)
+            .and_then(|end| ico.get(offset..end))
+            .ok_or_else(|| invalid_ico("image data lies outside the file"))?;
+        let mut header = [0; 8];
+        header.copy_from_slice(&entry[..8]);
+        images.push((header, data));
+    }
+    if images.is_empty() {
+        return Err(invalid_ico("the icon contains no images"));
+    }
+    Ok(images)
+}
+
+// Writes `resources` into a resource section and points the resource directory at it. The
+// current resource section is rewritten when it is the last one in the file and holds nothing
+// else, so patching the same file again does not make it grow; otherwise a new section is appended.
+fn write_resource_section(exe: &[u8], headers: &Headers, resources: &[Resource]) -> Result<Vec<u8>, Error> {
+    let optional = headers.optional;
+    let section_alignment = u32_le(exe, optional + 32) as usize;
+    let file_alignment = u32_le(exe, optional + 36) as usize;
+    // The limits the PE format sets; anything else is corrupt and could make the padding huge
+    if !file_alignment.is_power_of_two()
+        || !(512..=0x10000).contains(&file_alignment)
+        || !section_alignment.is_power_of_two()
+        || section_alignment < file_alignment
+    {
+        return Err(invalid(format!(
+            "unsupported section alignment {:#x} / file alignment {:#x}",
+            section_alignment, file_alignment
+        )));
+    }
+    let size_of_headers = u32_le(exe, optional + 60) as usize;
+    if headers.data_directory_count <= RESOURCE_DIRECTORY_INDEX {
+        return Err(invalid("the file has no resource directory entry"));
+    }
+
+    let mut out = exe.to_vec();
+    // The signature no longer matches; drop it rather than leave it in front of the new data. It
+    // is the only data directory holding a file offset, and it has to come after the sections.
+    let (certificate_offset, certificate_size) = headers.data_directory(exe, CERTIFICATE_DIRECTORY_INDEX);
+    if certificate_size != 0 {
+        let (certificate_offset, certificate_size) = (certificate_offset as usize, certificate_size as usize);
+        let sections_end = headers
+            .sections
+            .iter()
+            .map(|section| section.raw_offset as usize + section.raw_size as usize)
+            .max()
+            .unwrap_or(size_of_headers);
+        if certificate_offset < sections_end || certificate_offset > out.len() {
+            return Err(invalid("the signature overlaps the headers or sections"));
+        }
+        if certificate_offset + certificate_size >= out.len() {
+            out.truncate(certificate_offset);
+        }
+        put_u32(&mut out, headers.data_directories + CERTIFICATE_DIRECTORY_INDEX * 8, 0);
+        put_u32(&mut out, headers.data_directories + CERTIFICATE_DIRECTORY_INDEX * 8 + 4, 0);
+    }
+
+    // The loader wants every section to follow the previous one without a gap
+    let section_end = |section: &Section| {
+        let size = if section.virtual_size == 0 { section.raw_size } else { section.virtual_size };
+        section.virtual_address as usize + size as usize
+    };
+    let (old_rva, _) = headers.data_directory(exe, RESOURCE_DIRECTORY_INDEX);
+    let image_end = headers.sections.iter().map(section_end).max().unwrap_or(size_of_headers);
+    // Only a section holding nothing but the resources can be overwritten
+    let shares_section = |section: &Section| {
+        (0..headers.data_directory_count)
+            .filter(|&index| index != RESOURCE_DIRECTORY_INDEX && index != CERTIFICATE_DIRECTORY_INDEX)
+            .map(|index| headers.data_directory(exe, index))
+            .any(|(rva, size)| rva != 0 && size != 0 && section.contains_rva(rva))
+    };
+    let reused = headers.sections.last().filter(|section| {
+        old_rva != 0
+            && old_rva == section.virtual_address
+            && !shares_section(section)
+            && section_end(section) == image_end
+            && section.raw_offset as usize + section.raw_size as usize >= out.len()
+    });
+
+    let (index, virtual_address, old_raw_size) = match reused {
+        Some(section) => {
+            out.truncate(section.raw_offset as usize);
+            (headers.sections.len() - 1, section.virtual_address as usize, section.raw_size as usize)
+        }
+        None => {
+            let first_section_data = headers
+                .sections
+                .iter()
+                .filter(|section| section.raw_size > 0)
+                .map(|section| section.raw_offset as usize)
+                .min()
+                .unwrap_or(size_of_headers);
+            let table_end = headers.section_table + (headers.sections.len() + 1) * SECTION_HEADER_LEN;
+            if table_end > size_of_headers.min(first_section_data) {
+                return Err(invalid("no room in the headers for another section"));
+            }
+            // Keep the old section's name from suggesting it still holds the resources
+            for (index, section) in headers.sections.iter().enumerate() {
+                let name = headers.section_table + index * SECTION_HEADER_LEN;
+                if section.contains_rva(old_rva) && &out[name..name + 8] == b".rsrc\0\0\0" {
+                    out[name..name + 8].copy_from_slice(b".oldrsrc");
+                }
+            }
+            // NumberOfSections sits in the COFF header right before the optional header
+            put_u16(&mut out, optional - 18, headers.sections.len() as u16 + 1);
+            (headers.sections.len(), align(image_end, section_alignment), 0)
+        }
+    };
+
+    let section_data = build_resource_section(resources, virtual_address as u32);
+    let raw_offset = align(out.len(), file_alignment);
+    out.resize(raw_offset, 0);
+    out.extend_from_slice(&section_data);
+    out.resize(align(out.len(), file_alignment), 0);
+    let raw_size = out.len() - raw_offset;
+
+    let mut header = [0; SECTION_HEADER_LEN];
+    header[..8].copy_from_slice(b".rsrc\0\0\0");
+    header[8..12].copy_from_slice(&(section_data.len() as u32).to_le_bytes());
+    header[12..16].copy_from_slice(&(virtual_address as u32).to_le_bytes());
+    header[16..20].copy_from_slice(&(raw_size as u32).to_le_bytes());
+    header[20..24].copy_from_slice(&(raw_offset as u32).to_le_bytes());
+    // IMAGE_SCN_CNT_INITIALIZED_DATA | IMAGE_SCN_MEM_READ
+    header[36..40].copy_from_slice(&0x4000_0040u32.to_le_bytes());
+    let header_offset = headers.section_table + index * SECTION_HEADER_LEN;
+    out[header_offset..header_offset + SECTION_HEADER_LEN].copy_from_slice(&header);
+
+    let initialized_data = (u32_le(&out, optional + 8) as usize + raw_size).saturating_sub(old_raw_size);
+    put_u32(&mut out, optional + 8, initialized_data as u32);
+    put_u32(&mut out, optional + 56, align(virtual_address + section_data.len(), section_alignment) as u32);
+    put_u32(&mut out, headers.data_directories + RESOURCE_DIRECTORY_INDEX * 8, virtual_address as u32);
+    put_u32(&mut out, headers.data_directories + RESOURCE_DIRECTORY_INDEX * 8 + 4, section_data.len() as u32);
+    let checksum = pe_checksum(&out, optional + 64);
+    put_u32(&mut out, optional + 64, checksum);
+    Ok(out)
+}
+
+// The languages of one resource name, as a level of the resource tree
+type NamedResources<'a> = (&'a ResourceName, Vec<&'a Resource>);
+
+// Serializes the resource tree the way resource compilers do: all directory tables first, then
+// the data entries, the name strings and finally the data, each blob aligned to 8 bytes
+fn build_resource_section(resources: &[Resource], rva: u32) -> Vec<u8> {
+    let mut sorted: Vec<&Resource> = resources.iter().collect();
+    sorted.sort_by(|a, b| {
+        name_order(&a.kind, &b.kind)
+            .then_with(|| name_order(&a.name, &b.name))
+            .then(a.language.cmp(&b.language))
+    });
+    let mut tree: Vec<(&ResourceName, Vec<NamedResources>)> = Vec::new();
+    for resource in sorted {
+        match tree.last_mut() {
+            Some((kind, names)) if **kind == resource.kind => match names.last_mut() {
+                Some((name, leaves)) if **name == resource.name => leaves.push(resource),
+                _ => names.push((&resource.name, vec![resource])),
+            },
+            _ => tree.push((&resource.kind, vec![(&resource.name, vec![resource])])),
+        }
+    }
+
+    let directory_len = |count: usize| 16 + 8 * count;
+    let type_directories = directory_len(tree.len());
+    let name_directories: usize = tree.iter().map(|(_, names)| directory_len(names.len())).sum();
+    let language_directories: usize =
+        tree.iter().flat_map(|(_, names)| names).map(|(_, leaves)| directory_len(leaves.len())).sum();
+    let data_entries = type_directories + name_directories + language_directories;
+    let strings = data_entries + 16 * resources.len();
+
+    let mut section = vec![0; strings];
+    let mut string_table = Vec::new();
+    let mut name_field = |name: &ResourceName| match name {
+        ResourceName::Id(id) => u32::from(*id),
+        ResourceName::Name(text) => {
+            let offset = strings + string_table.len();
+            let units: Vec<u16> = text.encode_utf16().collect();
+            string_table.extend_from_slice(&(units.len() as u16).to_le_bytes());
+            string_table.extend(units.iter().flat_map(|unit| unit.to_le_bytes()));
+            0x8000_0000 | offset as u32
+        }
+    };
+
+    let mut next_name_directory = type_directories;
+    let mut next_language_directory = type_directories + name_directories;
+    let mut next_data_entry = data_entries;
+    let mut leaves_in_order = Vec::with_capacity(resources.len());
+    let mut type_entries = Vec::with_capacity(tree.len());
+    for (kind, names) in &tree {
+        let name_directory = next_name_directory;
+        next_name_directory += directory_len(names.len());
+        type_entries.push((name_field(kind), 0x8000_0000 | name_directory as u32));
+        let mut name_entries = Vec::with_capacity(names.len());
+        for (name, leaves) in names {
+            let language_directory = next_language_directory;
+            next_language_directory += directory_len(leaves.len());
+            name_entries.push((name_field(name), 0x8000_0000 | language_directory as u32));
+            let mut language_entries = Vec::with_capacity(leaves.len());
+            for leaf in leaves {
+                language_entries.push((u32::from(leaf.language), next_data_entry as u32));
+                leaves_in_order.push((*leaf, next_data_entry));
+                next_data_entry += 16;
+            }
+            write_directory(&mut section, language_directory, &language_entries);
+        }
+        write_directory(&mut section, name_directory, &name_entries);
+    }
+    write_directory(&mut section, 0, &type_entries);
+
+    section.extend_from_slice(&string_table);
+    for (leaf, data_entry) in leaves_in_order {
+        section.resize(align(section.len(), 8), 0);
+        let offset = section.len();
+        section.extend_from_slice(&leaf.data);
+        put_u32(&mut section, data_entry, rva + offset as u32);
+        put_u32(&mut section, data_entry + 4, leaf.data.len() as u32);
+        put_u32(&mut section, data_entry + 8, leaf.code_page);
+    }
+    section.resize(align(section.len(), 8), 0);
+    section
+}
+
+// Named entries come before numbered ones, names compared case-insensitively and IDs ascending
+fn name_order(a: &ResourceName, b: &ResourceName) -> std::cmp::Ordering {
+    match (a, b) {
+        (ResourceName::Name(a), ResourceName::Name(b)) => a.to_uppercase().cmp(&b.to_uppercase()),
+        (ResourceName::Name(_), ResourceName::Id(_)) => std::cmp::Ordering::Less,
+        (ResourceName::Id(_), ResourceName::Name(_)) => std::cmp::Ordering::Greater,
+        (ResourceName::Id(a), ResourceName::Id(b)) => a.cmp(b),
+    }
+}
+
+// Writes an IMAGE_RESOURCE_DIRECTORY with its (name or ID, target) entries, named ones first
+fn write_directory(section: &mut [u8], offset: usize, entries: &[(u32, u32)]) {
+    let named = entries.iter().filter(|(name, _)| name & 0x8000_0000 != 0).count();
+    put_u16(section, offset + 12, named as u16);
+    put_u16(section, offset + 14, (entries.len() - named) as u16);
+    for (index, &(name, target)) in entries.iter().enumerate() {
+        put_u32(section, offset + 16 + index * 8, name);
+        put_u32(section, offset + 20 + index * 8, target);
+    }
+}
+
+// The checksum the Windows loader verifies for drivers and some system DLLs: a 16-bit
+// one's-complement style sum of the file, skipping the checksum field, plus the file length
+fn pe_checksum(data: &[u8], checksum_offset: usize) -> u32 {
+    let mut sum: u32 = 0;
+    for (index, word) in data.chunks(2).enumerate() {
+        if index * 2 == checksum_offset || index * 2 == checksum_offset + 2 {
+            continue;
+        }
+        sum += u32::from(u16::from_le_bytes([word[0], word.get(1).copied().unwrap_or(0)]));
+        sum = (sum & 0xFFFF) + (sum >> 16);
+    }
+    sum.wrapping_add(data.len() as u32)
+}
+
+fn align(value: usize, alignment: usize) -> usize {
+    value.div_ceil(alignment) * alignment
+}
+
+fn put_u16(data: &mut [u8], offset: usize, value: u16) {
+    data[offset..offset + 2].copy_from_slice(&value.to_le_bytes());
+}
+
+fn put_u32(data: &mut [u8], offset: usize, value: u32) {
+    data[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+// Lays out an ICO file from the first 8 bytes of each group entry (size, colors, planes, bit
+// depth) and the matching image data
+fn build_ico(entries: &[(&[u8], &Vec<u8>)]) -> Vec<u8> {
+    let mut ico = Vec::new();
+    ico.extend_from_slice(&[0, 0, 1, 0]);
+    ico.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+    let mut offset = 6 + 16 * entries.len();
+    for (header, data) in entries {
+        ico.extend_from_slice(header);
+        ico.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        ico.extend_from_slice(&(offset as u32).to_le_bytes());
+        offset += data.len();
+    }
+    for (_, data) in entries {
+        ico.extend_from_slice(data);
+    }
+    ico
+}
+
+// One leaf of the resource tree: type / name / language and its data
+#[derive(Debug, Clone)]
+struct Resource {
+    kind: ResourceName,
+    name: ResourceName,
+    language: u16,
+    code_page: u32,
+    data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Section {
+    virtual_address: u32,
+    virtual_size: u32,
+    raw_size: u32,
+    raw_offset: u32,
+}
+
+// Where the parts of the PE headers are, as file offsets
+#[derive(Debug)]
+struct Headers {
+    optional: usize,
+    section_table: usize,
+    data_directories: usize,
+    data_directory_count: usize,
+    sections: Vec<Section>,
+    file_len: usize,
+}
+
+fn invalid(msg: impl Into<String>) -> Error {
+    Error::InvalidPe(msg.into())
+}
+
+fn read_headers(data: &[u8]) -> Result<Headers, Error> {
+    if data.len() < 0x40 || &data[..2] != b"MZ" {
+        return Err(invalid("missing MZ header"));
+    }
+    let pe_offset = u32_le(data, 0x3C) as usize;
+    if data.get(pe_offset..pe_offset + 4) != Some(b"PE\0\0") {
+        return Err(invalid("missing PE signature"));
+    }
+    let coff = pe_offset + 4;
+    let coff_header = data.get(coff..coff + 20).ok_or_else(|| invalid("COFF header is truncated"))?;
+    let section_count = u16_le(coff_header, 2) as usize;
+    let optional_size = u16_le(coff_header, 16) as usize;
+    let optional = coff + 20;
+    let magic = data.get(optional..optional + 2).map(|magic| u16_le(magic, 0));
+    // PE32 and PE32+ differ in the width of a few fields before the data directories
+    let (count_offset, directories_offset) = match magic {
+        Some(0x10B) => (92, 96),
+        Some(0x20B) => (108, 112),
+        _ => return Err(invalid("unknown optional header format")),
+    };
+    if optional_size < directories_offset || data.len() < optional + optional_size {
+        return Err(invalid("optional header is truncated"));
+    }
+    let data_directory_count = (u32_le(data, optional + count_offset) as usize).min((optional_size - directories_offset) / 8);
+
+    let section_table = optional + optional_size;
+    let table = data
+        .get(section_table..section_table + section_count * SECTION_HEADER_LEN)
+        .ok_or_else(|| invalid("section table is truncated"))?;
+    let sections = table
+        .chunks_exact(SECTION_HEADER_LEN)
+        .map(|header| Section {
+            virtual_size: u32_le(header, 8),
+            virtual_address: u32_le(header, 12),
+            raw_size: u32_le(header, 16),
+            raw_offset: u32_le(header, 20),
+        })
+        .collect();
+    Ok(Headers {
+        optional,
+        section_table,
+        data_directories: optional + directories_offset,
+        data_directory_count,
+        sections,
+        file_len: data.len(),
+    })
+}
+
+impl Section {
+    fn contains_rva(&self, rva: u32) -> bool {
+        let size = self.virtual_size.max(self.raw_size);
+        rva >= self.virtual_address && rva - self.virtual_address < size
+    }
+}
+
+impl Headers {
+    // (RVA, size) of a data directory; (0, 0) if the file has none
+    fn data_directory(&self, data: &[u8], index: usize) -> (u32, u32) {
+        if index >= self.data_directory_count {
+            return (0, 0);
+        }
+        let offset = self.data_directories + index * 8;
+        (u32_le(data, offset), u32_le(data, offset + 4))
+    }
+
+    fn rva_to_offset(&self, rva: u32) -> Option<usize> {
+        let section = self.sections.iter().find(|section| section.contains_rva(rva))?;
+        let delta = rva - section.virtual_address;
+        // Bytes past the raw data only exist in memory (zero-filled), not in the file, and a
+        // truncated file may end before the section does
+        let offset = section.raw_offset as usize + delta as usize;
+        (delta < section.raw_size && offset < self.file_len).then_some(offset)
+    }
+}
+
+// Flattens the three-level resource tree (type, name, language) into its leaves
+fn read_resources(data: &[u8]) -> Result<Vec<Resource>, Error> {
+    let headers = read_headers(data)?;
+    let (rva, _) = headers.data_directory(data, RESOURCE_DIRECTORY_INDEX);
+    if rva == 0 {
+        return Ok(Vec::new());
+    }
+    let root = headers.rva_to_offset(rva).ok_or_else(|| invalid("resource directory lies outside the file"))?;
+    let section_data = data.get(root..).ok_or_else(|| invalid("resource directory lies outside the file"))?;
+
+    // Each directory and data entry may only be reached once, and directories only point forward,
+    // so a corrupt tree cannot loop or multiply its entries
+    let mut visited = HashSet::from([0]);
+    let mut visit = |parent: usize, offset: usize| {
+        if offset <= parent || !visited.insert(offset) {
+            return Err(invalid("resource directories refer back to themselves"));
+        }
+        Ok(offset)
+    };
+    let mut resources = Vec::new();
+    for (kind, types) in read_directory(section_data, 0)? {
+        let DirectoryEntry::Directory(names) = types else { continue };
+        let names = visit(0, names)?;
+        for (name, languages) in read_directory(section_data, names)? {
+            let DirectoryEntry::Directory(languages) = languages else { continue };
+            let languages = visit(names, languages)?;
+            for (language, leaf) in read_directory(section_data, languages)? {
+                let (DirectoryEntry::Data(leaf), ResourceName::Id(language)) = (leaf, language) else { continue };
+                let leaf = visit(languages, leaf)?;
+                if resources.len() == MAX_RESOURCES {
+                    return Err(invalid("too many resources"));
+                }
+                let entry = section_data.get(leaf..leaf + 16).ok_or_else(|| invalid("resource data entry is truncated"))?;
+                let (data_rva, size, code_page) = (u32_le(entry, 0), u32_le(entry, 4) as usize, u32_le(entry, 8));
+                let contents = headers
+                    .rva_to_offset(data_rva)
+                    .and_then(|offset| data.get(offset..offset + size))
+                    .ok_or_else(|| invalid(format!("data of resource {} / {} lies outside the file", kind, name)))?;
+                resources.push(Resource { kind: kind.clone(), name: name.clone(), language, code_page, data: contents.to_vec() });
+            }
+        }
+    }
+    Ok(resources)
+}
+
+enum DirectoryEntry {
+    Directory(usize),
+    Data(usize),
+}
+
+// Reads the entries of the IMAGE_RESOURCE_DIRECTORY at `offset`, relative to the resource root
+fn read_directory(section: &[u8], offset: usize) -> Result<Vec<(ResourceName, DirectoryEntry)>, Error> {
+    let header = section.get(offset..offset + 16).ok_or_else(|| invalid("resource directory is truncated"))?;
+    let count = u16_le(header, 12) as usize + u16_le(header, 14) as usize;
+    let entries = section
+        .get(offset + 16..offset + 16 + count * 8)
+        .ok_or_else(|| invalid("resource directory is truncated"))?;
+    entries
+        .chunks_exact(8)
+        .map(|entry| {
+            let (name, target) = (u32_le(entry, 0), u32_le(entry, 4));
+            let name = if name & 0x8000_0000 != 0 {
+                ResourceName::Name(read_name(section, (name & 0x7FFF_FFFF) as usize)?)
+            } else {
+                ResourceName::Id(name as u16)
+            };
+            let target = if target & 0x8000_0000 != 0 {
+                DirectoryEntry::Directory((target & 0x7FFF_FFFF) as usize)
+            } else {
+                DirectoryEntry::Data(target as usize)
+            };
+            Ok((name, target))
+        })
+        .collect()
+}
+
+// Resource names are a UTF-16 length followed by that many UTF-16 code units
+fn read_name(section: &[u8], offset: usize) -> Result<String, Error> {
+    let length = section.get(offset..offset + 2).map(|length| u16_le(length, 0) as usize);
+    let units = length
+        .and_then(|length| section.get(offset + 2..offset + 2 + length * 2))
+        .ok_or_else(|| invalid("resource name is truncated"))?;
+    let units: Vec<u16> = units.chunks_exact(2).map(|unit| u16::from_le_bytes([unit[0], unit[1]])).collect();
+    Ok(String::from_utf16_lossy(&units))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RESOURCE_RVA: u32 = 0x1000;
+    const RESOURCE_OFFSET: usize = 0x200;
+    // File offset of the data directories in the PE32+ headers `minimal_pe` writes
+    const DATA_DIRECTORIES: usize = 0x44 + 20 + 112;
+
+    fn resource(kind: u16, name: ResourceName, data: Vec<u8>) -> Resource {
+        Resource { kind: ResourceName::Id(kind), name, language: 1033, code_page: 0, data }
+    }
+
+    // A PE32+ file with a single `.rsrc` section holding `resources`
+    fn minimal_pe(resources: &[Resource]) -> Vec<u8> {
+        let section_data = build_resource_section(resources, RESOURCE_RVA);
+        let mut pe = vec![0; RESOURCE_OFFSET];
+        pe[..2].copy_from_slice(b"MZ");
+        put_u32(&mut pe, 0x3C, 0x40);
+        pe[0x40..0x44].copy_from_slice(b"PE\0\0");
+        let coff = 0x44;
+        put_u16(&mut pe, coff, 0x8664);
+        put_u16(&mut pe, coff + 2, 1);
+        put_u16(&mut pe, coff + 16, 240);
+        let optional = coff + 20;
+        put_u16(&mut pe, optional, 0x20B);
+        put_u32(&mut pe, optional + 32, 0x1000);
+        put_u32(&mut pe, optional + 36, 0x200);
+        put_u32(&mut pe, optional + 56, align(RESOURCE_RVA as usize + section_data.len(), 0x1000) as u32);
+        put_u32(&mut pe, optional + 60, RESOURCE_OFFSET as u32);
+        put_u32(&mut pe, optional + 108, 16);
+        put_u32(&mut pe, DATA_DIRECTORIES + RESOURCE_DIRECTORY_INDEX * 8, RESOURCE_RVA);
+        put_u32(&mut pe, DATA_DIRECTORIES + RESOURCE_DIRECTORY_INDEX * 8 + 4, section_data.len() as u32);
+
+        let raw_size = align(section_data.len(), 0x200);
+        let section = optional + 240;
+        pe[section..section + 8].copy_from_slice(b".rsrc\0\0\0");
+        put_u32(&mut pe, section + 8, section_data.len() as u32);
+        put_u32(&mut pe, section + 12, RESOURCE_RVA);
+        put_u32(&mut pe, section + 16, raw_size as u32);
+        put_u32(&mut pe, section + 20, RESOURCE_OFFSET as u32);
+        pe.extend_from_slice(&section_data);
+        pe.resize(RESOURCE_OFFSET + raw_size, 0);
+        pe
+    }
+
+    // An RT_GROUP_ICON directory with one 16 x 16 and one 32 x 32 entry using icons 1 and 2
+    fn group_dir() -> Vec<u8> {
+        let mut dir = vec![0, 0, 1, 0, 2, 0];
+        for (size, id) in [(16u8, 1u16), (32, 2)] {
+            dir.extend_from_slice(&[size, size, 0, 0, 1, 0, 32, 0]);
+            dir.extend_from_slice(&4u32.to_le_bytes());
+            dir.extend_from_slice(&id.to_le_bytes());
+        }
+        dir
+    }
+
+    fn icon_resources(group_name: ResourceName) -> Vec<Resource> {
+        vec![
+            resource(RT_GROUP_ICON, group_name, group_dir()),
+            resource(RT_ICON, ResourceName::Id(1), vec![1; 4]),
+            resource(RT_ICON, ResourceName::Id(2), vec![2; 4]),
+        ]
+    }
+
+    #[test]
+    fn reads_icon_group() {
+        let groups = read_icon_groups(&minimal_pe(&icon_resources(ResourceName::Name("APP".to_string())))).unwrap();
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].name, ResourceName::Name("APP".to_string()));
+        assert_eq!(groups[0].language, 1033);
+        let images = read_ico_images(&groups[0].ico).unwrap();
+        assert_eq!(images.len(), 2);
+        assert_eq!((images[0].0[0], images[0].1), (16, &[1; 4][..]));
+        assert_eq!((images[1].0[0], images[1].1), (32, &[2; 4][..]));
+    }
+
+    #[test]
+    fn rejects_truncated_section() {
+        let pe = minimal_pe(&icon_resources(ResourceName::Id(1)));
+        // Cut inside the headers, before and at the start of the section, and inside its directories and data
+        for len in [0x100, 0x180, RESOURCE_OFFSET, RESOURCE_OFFSET + 0x10, pe.len() - 0x180] {
+            assert!(matches!(read_icon_groups(&pe[..len]), Err(Error::InvalidPe(_))), "length {:#x}", len);
+        }
+    }
+
+    #[test]
+    fn rejects_bad_name_offset() {
+        let mut pe = minimal_pe(&[resource(RT_GROUP_ICON, ResourceName::Name("APP".to_string()), group_dir())]);
+        // The only name entry follows the type directory (16 + 8 bytes) and its own 16-byte header
+        let name_entry = RESOURCE_OFFSET + 24 + 16;
+        assert_eq!(u32_le(&pe, name_entry) & 0x8000_0000, 0x8000_0000);
+        put_u32(&mut pe, name_entry, 0x8000_FFFF);
+        assert!(matches!(read_icon_groups(&pe), Err(Error::InvalidPe(_))));
+    }
+
+    // An ICO with a 16 x 16 and a 48 x 48 image
+    fn new_ico() -> Vec<u8> {
+        build_ico(&[(&[16, 16, 0, 0, 1, 0, 32, 0], &vec![7; 6]), (&[48, 48, 0, 0, 1, 0, 32, 0], &vec![8; 5])])
+    }
+
+    fn section_count(pe: &[u8]) -> u16 {
+        u16_le(pe, 0x44 + 2)
+    }
+
+    fn assert_new_icon(pe: &[u8]) {
+        let groups = read_icon_groups(pe).unwrap();
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].name, ResourceName::Id(1));
+        assert_eq!(groups[0].language, 1033);
+        assert_eq!(read_ico_images(&groups[0].ico).unwrap(), read_ico_images(&new_ico()).unwrap());
+    }
+
+    #[test]
+    fn replaces_icon_in_place() {
+        let pe = minimal_pe(&icon_resources(ResourceName::Id(1)));
+        let patched = replace_icon(&pe, &new_ico()).unwrap();
+        assert_eq!(section_count(&patched), 1);
+        assert_new_icon(&patched);
+        // Patching again rewrites the same section
+        let again = replace_icon(&patched, &new_ico()).unwrap();
+        assert_eq!(again.len(), patched.len());
+        assert_new_icon(&again);
+    }
+
+    #[test]
+    fn replaces_icon_in_new_section_when_shared() {
+        let mut pe = minimal_pe(&icon_resources(ResourceName::Id(1)));
+        // Let the import directory point into the resource section, as packed files do
+        let imports = DATA_DIRECTORIES + 8;
+        put_u32(&mut pe, imports, RESOURCE_RVA + 0x100);
+        put_u32(&mut pe, imports + 4, 0x40);
+        let patched = replace_icon(&pe, &new_ico()).unwrap();
+        assert_eq!(section_count(&patched), 2);
+        assert_eq!(&patched[RESOURCE_OFFSET..pe.len()], &pe[RESOURCE_OFFSET..]);
+        assert_new_icon(&patched);
+    }
+
+    #[test]
+    fn rejects_certificate_inside_headers() {
+        let mut pe = minimal_pe(&icon_resources(ResourceName::Id(1)));
+        let certificate = DATA_DIRECTORIES + CERTIFICATE_DIRECTORY_INDEX * 8;
+        put_u32(&mut pe, certificate, 0x10);
+        put_u32(&mut pe, certificate + 4, 0x1000);
+        assert!(matches!(replace_icon(&pe, &new_ico()), Err(Error::InvalidPe(_))));
+    }
+
+    #[test]
+    fn rejects_self_referencing_directory() {
+        let pe = minimal_pe(&icon_resources(ResourceName::Id(1)));
+        // The first type entry follows the 16-byte root header; point it back at the root, and then
+        // at the next type's name directory, which a second type entry also uses
+        let first_type = RESOURCE_OFFSET + 16;
+        for target in [0, u32_le(&pe, first_type + 12) & 0x7FFF_FFFF] {
+            let mut pe = pe.clone();
+            put_u32(&mut pe, first_type + 4, 0x8000_0000 | target);
+            assert!(matches!(read_icon_groups(&pe), Err(Error::InvalidPe(_))), "target {:#x}", target);
+        }
+    }
+
+    #[test]
+    fn rejects_bad_alignments() {
+        let optional = 0x44 + 20;
+        // (section alignment, file alignment): not a power of two, too small, too large, sections below file
+        for (section, file) in [(0x1000, 0x300), (0x1000, 0x100), (0x8000_0000, 0x8000_0000), (0x200, 0x1000), (0, 0)] {
+            let mut pe = minimal_pe(&icon_resources(ResourceName::Id(1)));
+            put_u32(&mut pe, optional + 32, section);
+            put_u32(&mut pe, optional + 36, file);
+            assert!(matches!(replace_icon(&pe, &new_ico()), Err(Error::InvalidPe(_))), "{:#x} / {:#x}", section, file);
+        }
+    }
+}
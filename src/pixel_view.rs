@@ -0,0 +1,254 @@
+use iced::advanced::image as image_renderer;
+use iced::advanced::layout::{self, Layout};
+use iced::advanced::renderer::{self, Quad};
+use iced::advanced::widget::{self, tree, Widget};
+use iced::advanced::{Clipboard, Shell};
+use iced::widget::image::{FilterMethod, Handle};
+use iced::{event, mouse, Border, Color, Element, Event, Length, Rectangle, Size};
+
+// Side of one checkerboard square in screen pixels, independent of the zoom
+const CHECKER_SIZE: f32 = 8.0;
+
+type PixelCallback<'a, T, Message> = Box<dyn Fn(T) -> Message + 'a>;
+
+/// Shows an image pixel-exact, optionally magnified, and reports clicks and hovers in image pixel
+/// coordinates.
+pub struct PixelView<'a, Message> {
+    handle: Handle,
+    width: u32,
+    height: u32,
+    scale: f32,
+    grid: bool,
+    checkerboard: bool,
+    marker: Option<(u16, u16)>,
+    on_press: Option<Box<dyn Fn(u16, u16) -> Message + 'a>>,
+    on_hover: Option<PixelCallback<'a, Option<(u16, u16)>, Message>>,
+}
+
+// The pixel last reported to `on_hover`, so moves within one pixel are not re-published
+#[derive(Default)]
+struct HoverState {
+    pixel: Option<(u16, u16)>,
+}
+
+impl<'a, Message> PixelView<'a, Message> {
+    pub fn new(handle: Handle, width: u32, height: u32) -> Self {
+        PixelView {
+            handle,
+            width,
+            height,
+            scale: 1.0,
+            grid: false,
+            checkerboard: false,
+            marker: None,
+            on_press: None,
+            on_hover: None,
+        }
+    }
+
+    /// Draws every image pixel as a `scale` x `scale` block (nearest-neighbour).
+    pub fn scale(mut self, scale: f32) -> Self {
+        self.scale = scale.max(1.0);
+        self
+    }
+
+    /// Outlines each image pixel; only drawn from 4x up, below that the lines would hide the image.
+    pub fn grid(mut self, grid: bool) -> Self {
+        self.grid = grid;
+        self
+    }
+
+    /// Marks a pixel with a crosshair, e.g. a cursor hotspot.
+    pub fn marker(mut self, marker: Option<(u16, u16)>) -> Self {
+        self.marker = marker;
+        self
+    }
+
+    /// Called with the image pixel under the cursor when the image is clicked.
+    pub fn on_press(mut self, on_press: impl Fn(u16, u16) -> Message + 'a) -> Self {
+        self.on_press = Some(Box::new(on_press));
+        self
+    }
+
+    /// Draws a grey checkerboard behind the image so transparent and semi-transparent pixels stand out.
+    pub fn checkerboard(mut self, checkerboard: bool) -> Self {
+        self.checkerboard = checkerboard;
+        self
+    }
+
+    /// Called with the image pixel under the cursor whenever it changes, and `None` when the cursor leaves.
+    pub fn on_hover(mut self, on_hover: impl Fn(Option<(u16, u16)>) -> Message + 'a) -> Self {
+        self.on_hover = Some(Box::new(on_hover));
+        self
+    }
+
+    // Maps a screen position inside `bounds` to the image pixel it covers
+    fn pixel_at(&self, bounds: Rectangle, position: iced::Point) -> (u16, u16) {
+        let x = ((position.x - bounds.x) / self.scale).floor().clamp(0.0, (self.width - 1) as f32);
+        let y = ((position.y - bounds.y) / self.scale).floor().clamp(0.0, (self.height - 1) as f32);
+        (x as u16, y as u16)
+    }
+}
+
+impl<'a, Message, Theme, Renderer> Widget<Message, Theme, Renderer> for PixelView<'a, Message>
+where
+    Renderer: image_renderer::Renderer<Handle = Handle>,
+{
+    fn tag(&self) -> tree::Tag {
+        tree::Tag::of::<HoverState>()
+    }
+
+    fn state(&self) -> tree::State {
+        tree::State::new(HoverState::default())
+    }
+
+    fn size(&self) -> Size<Length> {
+        Size::new(
+            Length::Fixed(self.width as f32 * self.scale),
+            Length::Fixed(self.height as f32 * self.scale),
+        )
+    }
+
+    fn layout(&self, _tree: &mut widget::Tree, _renderer: &Renderer, _limits: &layout::Limits) -> layout::Node {
+        layout::Node::new(Size::new(self.width as f32 * self.scale, self.height as f32 * self.scale))
+    }
+
+    fn draw(
+        &self,
+        _tree: &widget::Tree,
+        renderer: &mut Renderer,
+        _theme: &Theme,
+        _style: &renderer::Style,
+        layout: Layout<'_>,
+        _cursor: mouse::Cursor,
+        viewport: &Rectangle,
+    ) {
+        let bounds = layout.bounds();
+        if self.checkerboard {
+            // Only the visible part is tiled; a zoomed 256 px entry would otherwise need millions of squares
+            if let Some(visible) = bounds.intersection(viewport) {
+                renderer.fill_quad(Quad { bounds: visible, ..Quad::default() }, Color::from_rgb8(0xFF, 0xFF, 0xFF));
+                let first_column = ((visible.x - bounds.x) / CHECKER_SIZE).floor() as u32;
+                let first_row = ((visible.y - bounds.y) / CHECKER_SIZE).floor() as u32;
+                let columns = (visible.width / CHECKER_SIZE).ceil() as u32 + 1;
+                let rows = (visible.height / CHECKER_SIZE).ceil() as u32 + 1;
+                for row in first_row..first_row + rows {
+                    for column in (first_column..first_column + columns).filter(|column| (column + row) % 2 == 1) {
+                        let square = Rectangle::new(
+                            iced::Point::new(bounds.x + column as f32 * CHECKER_SIZE, bounds.y + row as f32 * CHECKER_SIZE),
+                            Size::new(CHECKER_SIZE, CHECKER_SIZE),
+                        );
+                        if let Some(square) = square.intersection(&visible) {
+                            renderer.fill_quad(Quad { bounds: square, ..Quad::default() }, Color::from_rgb8(0xCC, 0xCC, 0xCC));
+                        }
+                    }
+                }
+            }
+        }
+        renderer.draw(self.handle.clone(), FilterMethod::Nearest, bounds);
+
+        if self.grid && self.scale >= 4.0 {
+            let color = Color::from_rgba(0.5, 0.5, 0.5, 0.6);
+            let vertical = (0..=self.width).map(|x| {
+                Rectangle::new(
+                    iced::Point::new(bounds.x + x as f32 * self.scale - 0.5, bounds.y),
+                    Size::new(1.0, bounds.height),
+                )
+            });
+            let horizontal = (0..=self.height).map(|y| {
+                Rectangle::new(
+                    iced::Point::new(bounds.x, bounds.y + y as f32 * self.scale - 0.5),
+                    Size::new(bounds.width, 1.0),
+                )
+            });
+            for line in vertical.chain(horizontal) {
+                renderer.fill_quad(Quad { bounds: line, ..Quad::default() }, color);
+            }
+        }
+
+        if let Some((x, y)) = self.marker {
+            let color = Color::from_rgba(1.0, 0.0, 0.0, 0.8);
+            let center_x = bounds.x + (x as f32 + 0.5) * self.scale;
+            let center_y = bounds.y + (y as f32 + 0.5) * self.scale;
+            let lines = [
+                Rectangle::new(iced::Point::new(bounds.x, center_y - 0.5), Size::new(bounds.width, 1.0)),
+                Rectangle::new(iced::Point::new(center_x - 0.5, bounds.y), Size::new(1.0, bounds.height)),
+            ];
+            for line in lines {
+                renderer.fill_quad(Quad { bounds: line, ..Quad::default() }, color);
+            }
+            let pixel = Rectangle::new(
+                iced::Point::new(bounds.x + x as f32 * self.scale - 1.0, bounds.y + y as f32 * self.scale - 1.0),
+                Size::new(self.scale + 2.0, self.scale + 2.0),
+            );
+            renderer.fill_quad(
+                Quad {
+                    bounds: pixel,
+                    border: Border { color, width: 1.0, radius: 0.0.into() },
+                    ..Quad::default()
+                },
+                Color::TRANSPARENT,
+            );
+        }
+    }
+
+    fn on_event(
+        &mut self,
+        tree: &mut widget::Tree,
+        event: Event,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        _renderer: &Renderer,
+        _clipboard: &mut dyn Clipboard,
+        shell: &mut Shell<'_, Message>,
+        _viewport: &Rectangle,
+    ) -> event::Status {
+        let bounds = layout.bounds();
+        match event {
+            Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left)) => {
+                if let (Some(on_press), Some(position)) = (&self.on_press, cursor.position_over(bounds)) {
+                    let (x, y) = self.pixel_at(bounds, position);
+                    shell.publish(on_press(x, y));
+                    return event::Status::Captured;
+                }
+            }
+            Event::Mouse(mouse::Event::CursorMoved { .. } | mouse::Event::CursorLeft) => {
+                if let Some(on_hover) = &self.on_hover {
+                    let pixel = cursor.position_over(bounds).map(|position| self.pixel_at(bounds, position));
+                    let state = tree.state.downcast_mut::<HoverState>();
+                    if state.pixel != pixel {
+                        state.pixel = pixel;
+                        shell.publish(on_hover(pixel));
+                    }
+                }
+            }
+            _ => {}
+        }
+        event::Status::Ignored
+    }
+
+    fn mouse_interaction(
+        &self,
+        _tree: &widget::Tree,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        _viewport: &Rectangle,
+        _renderer: &Renderer,
+    ) -> mouse::Interaction {
+        if self.on_press.is_some() && cursor.is_over(layout.bounds()) {
+            mouse::Interaction::Crosshair
+        } else {
+            mouse::Interaction::Idle
+        }
+    }
+}
+
+impl<'a, Message, Theme, Renderer> From<PixelView<'a, Message>> for Element<'a, Message, Theme, Renderer>
+where
+    Message: 'a,
+    Renderer: image_renderer::Renderer<Handle = Handle> + 'a,
+{
+    fn from(view: PixelView<'a, Message>) -> Self {
+        Element::new(view)
+    }
+}
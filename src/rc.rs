@@ -0,0 +1,88 @@
+//! Windows resource scripts (`.rc`) declaring an application icon and its version information.
+
+use crate::Error;
+
+/// The product details shown on the Details tab of a Windows executable's properties.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VersionInfo {
+    pub product_name: String,
+    /// Dotted version such as `1.2.3`; up to four numeric parts go into the binary version fields.
+    pub version: String,
+    pub company: String,
+    /// Shown by Task Manager and Explorer; the product name is used when this is empty.
+    pub description: String,
+}
+
+impl VersionInfo {
+    /// Takes the name, version, description and first author of the `[package]` section of a
+    /// `Cargo.toml`. The company is the author's name without its e-mail address.
+    pub fn from_cargo_toml(text: &str) -> Result<VersionInfo, Error> {
+        let manifest: toml::Table = text.parse().map_err(|err: toml::de::Error| Error::InvalidManifest(err.message().to_string()))?;
+        let package = manifest
+            .get("package")
+            .and_then(toml::Value::as_table)
+            .ok_or_else(|| Error::InvalidManifest("no [package] section".to_string()))?;
+        // Fields inherited from a workspace (`version.workspace = true`) are tables, not strings
+        let field = |key: &str| package.get(key).and_then(toml::Value::as_str).unwrap_or_default().to_string();
+        let company = package
+            .get("authors")
+            .and_then(toml::Value::as_array)
+            .and_then(|authors| authors.first())
+            .and_then(toml::Value::as_str)
+            .map(|author| author.split('<').next().unwrap_or_default().trim().to_string())
+            .unwrap_or_default();
+        Ok(VersionInfo {
+            product_name: field("name"),
+            version: field("version"),
+            company,
+            description: field("description"),
+        })
+    }
+}
+
+/// Builds a resource script that makes `icon_file` the application icon and, when `version` is
+/// given, adds a `VERSIONINFO` block. `icon_file` is written as is, so a bare file name is looked
+/// up next to the script.
+pub fn resource_script(icon_file: &str, version: Option<&VersionInfo>) -> String {
+    let mut script = format!("MAINICON ICON {}\n", rc_string(icon_file));
+    let Some(version) = version else {
+        return script;
+    };
+
+    let numbers = version_numbers(&version.version).map(|number| number.to_string()).join(",");
+    let description = if version.description.is_empty() { &version.product_name } else { &version.description };
+    let strings = [
+        ("CompanyName", &version.company),
+        ("FileDescription", description),
+        ("FileVersion", &version.version),
+        ("ProductName", &version.product_name),
+        ("ProductVersion", &version.version),
+    ];
+
+    script.push_str("\n1 VERSIONINFO\n");
+    script.push_str(&format!("FILEVERSION {}\nPRODUCTVERSION {}\n", numbers, numbers));
+    // VOS_NT_WINDOWS32 and VFT_APP, spelled out so the script needs no #include <winver.h>
+    script.push_str("FILEFLAGSMASK 0x3F\nFILEFLAGS 0x0\nFILEOS 0x40004\nFILETYPE 0x1\nFILESUBTYPE 0x0\n");
+    script.push_str("BEGIN\n    BLOCK \"StringFileInfo\"\n    BEGIN\n        BLOCK \"040904B0\"\n        BEGIN\n");
+    for (key, value) in strings.iter().filter(|(_, value)| !value.is_empty()) {
+        script.push_str(&format!("            VALUE \"{}\", {}\n", key, rc_string(value)));
+    }
+    // U.S. English, Unicode, matching the StringFileInfo block above
+    script.push_str("        END\n    END\n    BLOCK \"VarFileInfo\"\n    BEGIN\n        VALUE \"Translation\", 0x409, 1200\n    END\nEND\n");
+    script
+}
+
+// The four 16-bit fields of a binary version: `1.2.3-beta` gives 1,2,3,0
+fn version_numbers(version: &str) -> [u16; 4] {
+    let mut numbers = [0; 4];
+    let core = version.split(['-', '+']).next().unwrap_or_default();
+    for (number, part) in numbers.iter_mut().zip(core.split('.')) {
+        *number = part.trim().parse().unwrap_or(0);
+    }
+    numbers
+}
+
+// Quotes text as a resource script string literal, where backslashes are escapes and a quote is doubled
+fn rc_string(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\"\""))
+}
@@ -0,0 +1,99 @@
+use std::sync::{Arc, OnceLock};
+
+use image::ImageEncoder;
+use resvg::{tiny_skia, usvg};
+
+use crate::{ConvertOptions, Error};
+
+// Loading system fonts is slow, so it is done once and shared by every render
+fn font_database() -> Arc<usvg::fontdb::Database> {
+    static FONTS: OnceLock<Arc<usvg::fontdb::Database>> = OnceLock::new();
+    FONTS
+        .get_or_init(|| {
+            let mut fonts = usvg::fontdb::Database::new();
+            fonts.load_system_fonts();
+            Arc::new(fonts)
+        })
+        .clone()
+}
+
+/// A parsed SVG document that can be rasterized at any size without touching the filesystem.
+pub struct SvgRenderer {
+    tree: usvg::Tree,
+    padding: f32,
+    background: Option<[u8; 4]>,
+}
+
+impl SvgRenderer {
+    /// Parses SVG (or gzip-compressed SVGZ) bytes using the render settings in `options`.
+    pub fn new(svg: &[u8], options: &ConvertOptions) -> Result<SvgRenderer, Error> {
+        let usvg_options = usvg::Options {
+            dpi: options.dpi,
+            shape_rendering: if options.anti_alias {
+                usvg::ShapeRendering::GeometricPrecision
+            } else {
+                usvg::ShapeRendering::CrispEdges
+            },
+            fontdb: font_database(),
+            ..Default::default()
+        };
+        let tree = usvg::Tree::from_data(svg, &usvg_options).map_err(|err| Error::InvalidSvg(err.to_string()))?;
+        Ok(SvgRenderer {
+            tree,
+            padding: options.padding.clamp(0.0, 0.49),
+            background: options.background,
+        })
+    }
+
+    /// Writes the parsed document back out as a compact, self-contained SVG: comments, metadata and
+    /// editor cruft are dropped, text is converted to paths and numbers are rounded.
+    pub fn to_optimized_svg(&self) -> String {
+        let write_options = usvg::WriteOptions {
+            coordinates_precision: 3,
+            transforms_precision: 4,
+            indent: usvg::Indent::None,
+            attributes_indent: usvg::Indent::None,
+            ..Default::default()
+        };
+        self.tree.to_string(&write_options)
+    }
+
+    /// Renders a `size` x `size` image. Non-square artwork keeps its aspect ratio and is centered.
+    pub fn render(&self, size: u32) -> Result<image::RgbaImage, Error> {
+        let mut pixmap = tiny_skia::Pixmap::new(size, size).ok_or(Error::InvalidSize(size))?;
+        if let Some([r, g, b, a]) = self.background {
+            pixmap.fill(tiny_skia::Color::from_rgba8(r, g, b, a));
+        }
+
+        let svg_size = self.tree.size();
+        let available = size as f32 * (1.0 - 2.0 * self.padding);
+        let scale = available / svg_size.width().max(svg_size.height());
+        let offset_x = (size as f32 - svg_size.width() * scale) / 2.0;
+        let offset_y = (size as f32 - svg_size.height() * scale) / 2.0;
+        let transform = tiny_skia::Transform::from_scale(scale, scale).post_translate(offset_x, offset_y);
+        resvg::render(&self.tree, transform, &mut pixmap.as_mut());
+
+        // tiny-skia works in premultiplied alpha, ICO entries and PNGs are straight alpha
+        let rgba = pixmap
+            .pixels()
+            .iter()
+            .flat_map(|pixel| {
+                let color = pixel.demultiply();
+                [color.red(), color.green(), color.blue(), color.alpha()]
+            })
+            .collect();
+        Ok(image::RgbaImage::from_raw(size, size, rgba).expect("pixmap has size * size pixels"))
+    }
+}
+
+/// Encodes an RGBA image as a PNG file.
+pub(crate) fn encode_png(image: &image::RgbaImage) -> Result<Vec<u8>, Error> {
+    let mut png = Vec::new();
+    image::codecs::png::PngEncoder::new(&mut png).write_image(
+        image.as_raw(),
+        image.width(),
+        image.height(),
+        image::ColorType::Rgba8,
+    )?;
+    Ok(png)
+}
@@ -0,0 +1,75 @@
+//! Preferences kept between runs in `settings.toml` under the platform config directory.
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::ThemeMode;
+
+// Entries kept in the recent-files list
+const MAX_RECENT_FILES: usize = 10;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    // Checked icon sizes; `None` until the user first changes them
+    pub sizes: Option<Vec<u16>>,
+    // Sizes added through the custom size field, offered next to the standard ones
+    pub custom_sizes: Vec<u16>,
+    pub theme: ThemeMode,
+    // Folders the open and save dialogs were last used in
+    pub input_dir: Option<PathBuf>,
+    pub output_dir: Option<PathBuf>,
+    // Logical width and height of the window when it was closed
+    pub window_size: Option<(f32, f32)>,
+    // Opened and converted files, most recent first
+    pub recent_files: Vec<PathBuf>,
+}
+
+impl Settings {
+    /// `<config dir>/Rusty_SVG2ICO/settings.toml`, or `None` if the platform has no config directory.
+    pub fn path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("Rusty_SVG2ICO").join("settings.toml"))
+    }
+
+    /// Reads the settings file; a missing or unreadable file gives the defaults.
+    pub fn load() -> Settings {
+        Settings::path()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|contents| toml::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let Some(path) = Settings::path() else {
+            return Ok(());
+        };
+        let contents = toml::to_string(self).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, contents)
+    }
+
+    /// Moves `path` to the top of the recent-files list and remembers its folder for the open dialogs.
+    pub fn add_recent_file(&mut self, path: &Path) {
+        self.recent_files.retain(|other| other != path);
+        self.recent_files.insert(0, path.to_path_buf());
+        self.recent_files.truncate(MAX_RECENT_FILES);
+        self.input_dir = path.parent().map(Path::to_path_buf);
+    }
+}
+
+// A recent-files entry as listed in the picker: file name first, folder after it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecentFile(pub PathBuf);
+
+impl std::fmt::Display for RecentFile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.0.file_name(), self.0.parent()) {
+            (Some(name), Some(dir)) => write!(f, "{}  ({})", name.to_string_lossy(), dir.display()),
+            _ => write!(f, "{}", self.0.display()),
+        }
+    }
+}